@@ -1,4 +1,18 @@
 fn main() {
     // delete existing version file created by blink.download
     let _ = std::fs::remove_file("target/release/version");
+
+    #[cfg(feature = "ffi")]
+    generate_c_header();
+}
+
+/// Generates the header into `OUT_DIR`, where the ffi tests check that the checked-in
+/// `include/blink_pairs.h` is up to date
+#[cfg(feature = "ffi")]
+fn generate_c_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    cbindgen::generate(&crate_dir)
+        .expect("Unable to generate the C header")
+        .write_to_file(format!("{out_dir}/blink_pairs.h"));
 }
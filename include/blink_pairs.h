@@ -0,0 +1,252 @@
+#ifndef BLINK_PAIRS_H
+#define BLINK_PAIRS_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+enum BlinkPairsKind
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : uint8_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  BlinkPairsKind_Opening = 0,
+  BlinkPairsKind_Closing = 1,
+  BlinkPairsKind_NonPair = 2,
+  BlinkPairsKind_Middle = 3,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum BlinkPairsKind BlinkPairsKind;
+#else
+typedef uint8_t BlinkPairsKind;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+/**
+ * Same values as the `token_type` accepted by `get_line_matches` in Lua
+ */
+enum BlinkPairsTokenType
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : uint8_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  BlinkPairsTokenType_Delimiter = 0,
+  BlinkPairsTokenType_String = 1,
+  BlinkPairsTokenType_BlockString = 2,
+  BlinkPairsTokenType_LineComment = 3,
+  BlinkPairsTokenType_BlockComment = 4,
+  BlinkPairsTokenType_Marker = 5,
+  BlinkPairsTokenType_Region = 6,
+  BlinkPairsTokenType_Embedded = 7,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum BlinkPairsTokenType BlinkPairsTokenType;
+#else
+typedef uint8_t BlinkPairsTokenType;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+enum BlinkPairsStateKind
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : uint8_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  BlinkPairsStateKind_Normal = 0,
+  BlinkPairsStateKind_InString = 1,
+  BlinkPairsStateKind_InBlockString = 2,
+  BlinkPairsStateKind_InLineComment = 3,
+  BlinkPairsStateKind_InBlockComment = 4,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum BlinkPairsStateKind BlinkPairsStateKind;
+#else
+typedef uint8_t BlinkPairsStateKind;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+/**
+ * Parsed buffer, created with `blink_pairs_buffer_new` and freed with `blink_pairs_buffer_free`
+ */
+typedef struct BlinkPairsBuffer BlinkPairsBuffer;
+
+/**
+ * Borrowed UTF-8 string, not NUL terminated
+ */
+typedef struct BlinkPairsStr {
+  const char *ptr;
+  size_t len;
+} BlinkPairsStr;
+
+typedef struct BlinkPairsMatch {
+  BlinkPairsKind kind;
+  BlinkPairsTokenType token_type;
+  struct BlinkPairsStr opening;
+  /**
+   * Empty for tokens without a closing text, such as strings and line comments
+   */
+  struct BlinkPairsStr closing;
+  size_t line;
+  size_t col;
+  /**
+   * Length of the match in bytes
+   */
+  size_t len;
+  /**
+   * Only set for delimiters
+   */
+  bool has_stack_height;
+  size_t stack_height;
+  /**
+   * Height among the delimiters of the same type, only set for delimiters
+   */
+  bool has_type_stack_height;
+  size_t type_stack_height;
+} BlinkPairsMatch;
+
+/**
+ * Parser state at the end of a line
+ */
+typedef struct BlinkPairsState {
+  BlinkPairsStateKind kind;
+  /**
+   * Opening text of the string or comment, empty for `Normal` and `InLineComment`
+   */
+  struct BlinkPairsStr token;
+} BlinkPairsState;
+
+typedef struct BlinkPairsPair {
+  struct BlinkPairsMatch opening;
+  struct BlinkPairsMatch closing;
+} BlinkPairsPair;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Parses the lines with the language of the filetype. Returns NULL when the filetype is not
+ * supported, or when the filetype or lines are not valid UTF-8.
+ *
+ * # Safety
+ *
+ * `filetype` must be a valid NUL terminated string and `lines` must point to `line_count`
+ * valid strings. The strings only need to live for the duration of the call.
+ */
+struct BlinkPairsBuffer *blink_pairs_buffer_new(const char *filetype,
+                                                const struct BlinkPairsStr *lines,
+                                                size_t line_count);
+
+/**
+ * Frees a buffer created with `blink_pairs_buffer_new`. Passing NULL is a no-op.
+ *
+ * # Safety
+ *
+ * `buffer` must be NULL or a pointer returned by `blink_pairs_buffer_new` which hasn't been
+ * freed yet.
+ */
+void blink_pairs_buffer_free(struct BlinkPairsBuffer *buffer);
+
+/**
+ * Reparses the lines between `start_line` and `old_end_line` (exclusive), which have been
+ * replaced by `lines`, ending at `new_end_line` (exclusive). Matches the arguments of Neovim's
+ * `on_lines` callback. Pass `-1` for all three to reparse the whole buffer with `lines`.
+ * Returns false when the filetype is not supported, or when the range is outside of the buffer
+ * or covers more lines than given.
+ *
+ * # Safety
+ *
+ * `buffer` must be a valid buffer, `filetype` a valid NUL terminated string and `lines` must
+ * point to `line_count` valid strings.
+ */
+bool blink_pairs_buffer_reparse(struct BlinkPairsBuffer *buffer,
+                                const char *filetype,
+                                const struct BlinkPairsStr *lines,
+                                size_t line_count,
+                                int64_t start_line,
+                                int64_t old_end_line,
+                                int64_t new_end_line);
+
+/**
+ * # Safety
+ *
+ * `buffer` must be a valid buffer.
+ */
+size_t blink_pairs_buffer_line_count(const struct BlinkPairsBuffer *buffer);
+
+/**
+ * Writes up to `capacity` matches on the line into `out`, and returns the total number of
+ * matches on the line. `out` may be NULL to only query the count.
+ *
+ * # Safety
+ *
+ * `buffer` must be a valid buffer and `out` must be NULL or point to `capacity` writable
+ * matches.
+ */
+size_t blink_pairs_buffer_line_matches(const struct BlinkPairsBuffer *buffer,
+                                       size_t line,
+                                       struct BlinkPairsMatch *out,
+                                       size_t capacity);
+
+/**
+ * Writes the state at the end of the line into `out`. Returns false when the line is out of
+ * range.
+ *
+ * # Safety
+ *
+ * `buffer` must be a valid buffer and `out` must point to a writable state.
+ */
+bool blink_pairs_buffer_line_state(const struct BlinkPairsBuffer *buffer,
+                                   size_t line,
+                                   struct BlinkPairsState *out);
+
+/**
+ * Writes the match at the position into `out`. Returns false when there's no match.
+ *
+ * # Safety
+ *
+ * `buffer` must be a valid buffer and `out` must point to a writable match.
+ */
+bool blink_pairs_buffer_match_at(const struct BlinkPairsBuffer *buffer,
+                                 size_t line,
+                                 size_t col,
+                                 struct BlinkPairsMatch *out);
+
+/**
+ * Writes the pair of the match at the position into `out`. Returns false when there's no
+ * match, or it has no counterpart.
+ *
+ * # Safety
+ *
+ * `buffer` must be a valid buffer and `out` must point to a writable pair.
+ */
+bool blink_pairs_buffer_match_pair(const struct BlinkPairsBuffer *buffer,
+                                   size_t line,
+                                   size_t col,
+                                   struct BlinkPairsPair *out);
+
+/**
+ * Writes up to `capacity` pairs of the buffer into `out`, ordered by the position of the
+ * opening match, and returns the total number of pairs. `out` may be NULL to only query the
+ * count.
+ *
+ * # Safety
+ *
+ * `buffer` must be a valid buffer and `out` must be NULL or point to `capacity` writable
+ * pairs.
+ */
+size_t blink_pairs_buffer_pairs(const struct BlinkPairsBuffer *buffer,
+                                struct BlinkPairsPair *out,
+                                size_t capacity);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BLINK_PAIRS_H */
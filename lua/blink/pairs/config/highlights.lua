@@ -3,6 +3,8 @@
 --- @field groups string[]
 --- @field priority number
 --- @field ns integer
+--- @field first_branch_only boolean Calculate the rainbow levels from only the first branch of preprocessor conditionals (`#if` until `#else`), so that unbalanced braces in the other branches don't affect the code after `#endif`
+--- @field independent_levels boolean Calculate the rainbow levels of each type of delimiter independently, so that `(` inside of `{` starts from the first group
 --- @field matchparen blink.pairs.MatchparenConfig
 
 --- @class (exact) blink.pairs.MatchparenConfig
@@ -22,6 +24,8 @@ local highlights = {
     },
     priority = 200,
     ns = vim.api.nvim_create_namespace('blink.pairs'),
+    first_branch_only = false,
+    independent_levels = false,
     matchparen = {
       enabled = true,
       group = 'MatchParen',
@@ -36,6 +40,8 @@ function highlights.validate(config)
     groups = { config.groups, 'table' },
     priority = { config.priority, 'number' },
     ns = { config.ns, 'number' },
+    first_branch_only = { config.first_branch_only, 'boolean' },
+    independent_levels = { config.independent_levels, 'boolean' },
     matchparen = { config.matchparen, 'table', true },
   }, config)
 
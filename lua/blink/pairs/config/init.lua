@@ -1,6 +1,7 @@
 --- @class (exact) blink.pairs.ConfigStrict
 --- @field mappings blink.pairs.MappingsConfig
 --- @field highlights blink.pairs.HighlightsConfig
+--- @field languages table<string, blink.pairs.LanguageSpec | string> Languages to define at runtime, keyed by filetype. Either a spec or a path to a `.toml`/`.json` spec file
 --- @field debug boolean
 
 local validate = require('blink.pairs.config.utils').validate
@@ -8,6 +9,7 @@ local validate = require('blink.pairs.config.utils').validate
 local config = {
   mappings = require('blink.pairs.config.mappings').default,
   highlights = require('blink.pairs.config.highlights').default,
+  languages = {},
   debug = false,
 }
 
@@ -20,6 +22,7 @@ function M.validate(cfg)
   validate('config', {
     mappings = { cfg.mappings, 'table' },
     highlights = { cfg.highlights, 'table' },
+    languages = { cfg.languages, 'table' },
     debug = { cfg.debug, 'boolean' },
   }, cfg)
 
@@ -3,6 +3,8 @@
 --- @field pairs blink.pairs.RuleDefinitions
 
 local validate = require('blink.pairs.config.utils').validate
+local language = require('blink.pairs.language')
+
 local mappings = {
   --- @type blink.pairs.MappingsConfig
   default = {
@@ -20,9 +22,8 @@ local mappings = {
           when = function()
             local cursor = vim.api.nvim_win_get_cursor(0)
             local line = vim.api.nvim_get_current_line()
-            return line:sub(cursor[2] - 1, cursor[2]) == "''"
+            return line:sub(cursor[2] - 1, cursor[2]) == "''" and language.has_block_string("'''", "'''")
           end,
-          filetypes = { 'python' },
         },
         {
           "'",
@@ -36,16 +37,18 @@ local mappings = {
         },
       },
       ['"'] = {
-        { 'r#"', '"#', filetypes = { 'rust' }, priority = 100 },
+        { 'r#"', '"#', when = function() return language.has_block_string('r#"', '"#') end, priority = 100 },
         {
           '"""',
           '"""',
           when = function()
             local cursor = vim.api.nvim_win_get_cursor(0)
             local line = vim.api.nvim_get_current_line()
-            return line:sub(cursor[2] - 1, cursor[2]) == '""'
+            if line:sub(cursor[2] - 1, cursor[2]) ~= '""' then return false end
+
+            -- julia has no language definition in the parser
+            return language.has_block_string('"""', '"""') or vim.bo.filetype == 'julia'
           end,
-          filetypes = { 'python', 'elixir', 'julia', 'kotlin', 'scala', 'sbt' },
         },
         { '"', enter = false, space = false },
       },
@@ -1,6 +1,7 @@
 --- @class (exact) blink.pairs.Config : blink.pairs.ConfigStrict, {}
 --- @field mappings? blink.pairs.MappingsConfigPartial
 --- @field highlights? blink.pairs.HighlightsConfigPartial
+--- @field languages? table<string, blink.pairs.LanguageSpec | string>
 --- @field debug? boolean
 
 --- @class (exact) blink.pairs.MappingsConfigPartial : blink.pairs.MappingsConfig
@@ -23,3 +24,25 @@
 --- @field matchparen? blink.pairs.MatchparenConfigPartial
 
 --- @class (exact) blink.pairs.MatchparenConfigPartial : blink.pairs.MatchparenConfig, {}
+
+--- Same sections as `define_matcher!`. Chars must be single characters
+--- @class (exact) blink.pairs.LanguageSpec
+--- @field extends? string Filetype of the language to extend, i.e. 'c'
+--- @field without? string[] Sections of the extended language to remove, i.e. { 'char' }
+--- @field delimiters? [string, string][] i.e. { { '(', ')' }, { '{', '}' } }
+--- @field symmetric? table<string, 'none' | 'line'> Delimiters opening and closing with the same pattern, with whether they must be closed on the same line, i.e. { ['|'] = 'line' } for closure parameters
+--- @field generic? [string, string][] Angle brackets of generic arguments, only opening after an identifier or `::` and dropped when not closed on the same line, i.e. { { '<', '>' } }
+--- @field line_comment? string[] i.e. { '//' }
+--- @field block_comment? [string, string][] i.e. { { '/*', '*/' } }
+--- @field string? string[] i.e. { '"' }
+--- @field char? string[] i.e. { "'" }
+--- @field block_string? [string, string][] i.e. { { '"""', '"""' } }
+--- @field regex? [string, string][] Regex literals closed on the same line, i.e. { { '/', '/' } }
+--- @field quote_like? table<string, 1 | 2> Prefixes of quote-like operators with the delimiter chosen at the use site and their number of parts, i.e. { q = 1, s = 2 } for `q{..}` and `s/../../`
+--- @field marker? [string, string][] Line-based pairs, lexed from the line, i.e. { { '#if', '#endif' } }
+--- @field middle? [string, string][] Separators between the branches of a marker pair, with the closing marker of the pair, i.e. { { '#else', '#endif' } }
+--- @field region? [string, string][] Region markers found inside of line comments, i.e. { { '#region', '#endregion' }, { '{{{', '}}}' } }
+--- @field continuation? ('string' | 'line_comment')[] Sections continuing onto the next line when the line ends with a backslash, i.e. { 'string', 'line_comment' } in C
+--- @field embedded? ('string' | 'block_string' | 'line_comment' | 'block_comment')[] Sections in which delimiters are still matched, reported with a separate token type and stack, i.e. { 'block_string' } for regex groups in Rust strings
+--- @field escape? table<string, 'none' | 'backslash' | 'doubled'> Escape rule by opening delimiter of strings and block strings, defaults to 'backslash'
+--- @field anchor? table<string, table<string, 'line_start' | 'indent' | 'whitespace' | 'word' | 'word_start' | 'outside_number'>> Anchors by section and pattern, i.e. { line_comment = { ['#'] = 'whitespace' } }
@@ -6,9 +6,10 @@ function highlighter.register(config)
     on_win = function(_, _, bufnr) return require('blink.pairs.watcher').attach(bufnr) end,
     on_line = function(_, _, bufnr, line_number)
       for _, match in ipairs(require('blink.pairs.rust').get_line_matches(bufnr, line_number)) do
+        local stack_height = config.independent_levels and match.type_stack_height or match.stack_height
         vim.api.nvim_buf_set_extmark(bufnr, config.ns, line_number, match.col, {
-          end_col = match.col + match[0]:len(),
-          hl_group = config.groups[match.stack_height % #config.groups + 1],
+          end_col = match.col + match.len,
+          hl_group = config.groups[stack_height % #config.groups + 1],
           hl_mode = 'combine',
           priority = config.priority,
           ephemeral = true,
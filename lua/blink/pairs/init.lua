@@ -19,11 +19,38 @@ function pairs.setup(user_config)
   pairs.download_if_available(function(err)
     if err then error(err) end
 
+    pairs.register_runtime_languages()
+    for filetype, spec in next, config.languages do
+      pairs.register_language(filetype, spec)
+    end
+
     if config.mappings.enabled then require('blink.pairs.mappings').register(config.mappings.pairs) end
     if config.highlights.enabled then require('blink.pairs.highlighter').register(config.highlights) end
   end)
 end
 
+--- Defines the delimiters, comments and strings of a filetype without recompiling.
+--- Overrides the built-in definition when one exists for the filetype
+--- @param filetype string
+--- @param spec blink.pairs.LanguageSpec | string Spec or path to a `.toml` or `.json` spec file
+function pairs.register_language(filetype, spec)
+  require('blink.pairs.language').cache = {}
+  if type(spec) == 'string' then
+    require('blink_pairs').register_language_file(filetype, vim.fs.normalize(spec))
+  else
+    require('blink_pairs').register_language(filetype, spec)
+  end
+end
+
+--- Registers the spec files found at `blink-pairs/languages/<filetype>.{toml,json}` in the runtimepath
+function pairs.register_runtime_languages()
+  for _, extension in ipairs({ 'toml', 'json' }) do
+    for _, path in ipairs(vim.api.nvim_get_runtime_file('blink-pairs/languages/*.' .. extension, true)) do
+      pairs.register_language(vim.fn.fnamemodify(path, ':t:r'), path)
+    end
+  end
+end
+
 --- You may optionally use `blink.download` for prebuilt binaries with the included `Cross.toml`
 --- and `.github/workflows/release.yaml`
 function pairs.download_if_available(callback)
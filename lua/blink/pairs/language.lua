@@ -0,0 +1,33 @@
+--- Language definitions from the parser, so that auto-pair rules come from the same source as the
+--- highlighting
+local language = {
+  --- @type table<string, blink.pairs.LanguageSpec | false>
+  cache = {},
+}
+
+--- Spec of the language, with the sections of the languages it extends merged in
+--- @param filetype? string Defaults to the filetype of the current buffer
+--- @return blink.pairs.LanguageSpec?
+function language.get_spec(filetype)
+  filetype = filetype or vim.bo.filetype
+  if language.cache[filetype] == nil then
+    language.cache[filetype] = require('blink.pairs.rust').get_language_spec(filetype) or false
+  end
+  return language.cache[filetype] or nil
+end
+
+--- @param opening string
+--- @param closing string
+--- @param filetype? string Defaults to the filetype of the current buffer
+--- @return boolean
+function language.has_block_string(opening, closing, filetype)
+  local spec = language.get_spec(filetype)
+  if spec == nil then return false end
+
+  for _, pair in ipairs(spec.block_string) do
+    if pair[1] == opening and pair[2] == closing then return true end
+  end
+  return false
+end
+
+return language
@@ -16,9 +16,22 @@ local function parse_buffer(bufnr, start_line, old_end_line, new_end_line)
   local lines = vim.api.nvim_buf_get_lines(bufnr, start_line or 0, new_end_line or -1, false)
 
   local rust = require('blink.pairs.rust')
-  local did_parse = rust.parse_buffer(bufnr, vim.bo[bufnr].filetype, lines, start_line, old_end_line, new_end_line)
-
-  if did_parse and require('blink.pairs.config').debug then
+  -- unsupported filetypes fall back to basic pairing, taking comments from the commentstring,
+  -- except for special buffers (terminals, prompts, plugin UIs, etc.)
+  local commentstring = vim.bo[bufnr].buftype == '' and vim.bo[bufnr].commentstring or nil
+  local config = require('blink.pairs.config')
+  local did_parse = rust.parse_buffer(
+    bufnr,
+    vim.bo[bufnr].filetype,
+    lines,
+    start_line,
+    old_end_line,
+    new_end_line,
+    commentstring,
+    { first_branch_only = config.highlights.first_branch_only }
+  )
+
+  if did_parse and config.debug then
     vim.print('parsing time: ' .. (vim.uv.hrtime() - start_time) / 1e6 .. ' ms')
   end
 
@@ -1,18 +1,206 @@
 use syn::parse::{Parse, ParseStream};
-use syn::token::{Colon, Comma, FatArrow};
+use syn::token::{At, Colon, Comma, FatArrow};
 use syn::{braced, bracketed, Result};
-use syn::{Ident, LitStr};
+use syn::{Ident, LitInt, LitStr};
 
 use std::collections::HashSet;
 
+pub const SECTIONS: &[&str] = &[
+    "delimiters",
+    "symmetric",
+    "generic",
+    "line_comment",
+    "block_comment",
+    "string",
+    "char",
+    "block_string",
+    "regex",
+    "quote_like",
+    "marker",
+    "middle",
+    "region",
+    "continuation",
+    "embedded",
+    "escape",
+];
+
+/// Same as `QUOTE_LIKE_DELIMITERS` in the parser, delimiters that may follow the prefix of a
+/// quote-like operator
+pub const QUOTE_LIKE_DELIMITERS: &[(&str, &str)] = &[
+    ("(", ")"),
+    ("[", "]"),
+    ("{", "}"),
+    ("<", ">"),
+    ("/", "/"),
+    ("|", "|"),
+    ("!", "!"),
+    ("#", "#"),
+    ("\"", "\""),
+    ("'", "'"),
+    ("\"\"\"", "\"\"\""),
+    ("'''", "'''"),
+];
+
+/// How a closing string or block string delimiter may be escaped
+#[derive(Clone, Copy, PartialEq)]
+pub enum Escape {
+    /// Can't be escaped, i.e. Rust raw strings
+    None,
+    /// Preceded by a backslash, the default
+    Backslash,
+    /// Repeated twice, i.e. `''` in SQL strings
+    Doubled,
+}
+
+impl Escape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Escape::None => "None",
+            Escape::Backslash => "Backslash",
+            Escape::Doubled => "Doubled",
+        }
+    }
+}
+
+/// How far a symmetric delimiter may extend before its closing delimiter
+#[derive(Clone, Copy, PartialEq)]
+pub enum Limit {
+    None,
+    /// Dropped when left open at the end of the line
+    Line,
+}
+
+impl Limit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Limit::None => "None",
+            Limit::Line => "Line",
+        }
+    }
+}
+
+/// Constraint on the surrounding text of a pattern
+#[derive(Clone, Copy, PartialEq)]
+pub enum Anchor {
+    /// At the first column of the line
+    LineStart,
+    /// Only preceded by whitespace on the line
+    Indent,
+    /// At the start of the line or preceded by whitespace
+    Whitespace,
+    /// Not preceded nor followed by a word character
+    Word,
+    /// Not preceded by a word character
+    WordStart,
+    /// Not inside of a number
+    OutsideNumber,
+}
+
+impl Anchor {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Anchor::LineStart => "LineStart",
+            Anchor::Indent => "Indent",
+            Anchor::Whitespace => "Whitespace",
+            Anchor::Word => "Word",
+            Anchor::WordStart => "WordStart",
+            Anchor::OutsideNumber => "OutsideNumber",
+        }
+    }
+}
+
 pub struct MatcherDef {
     pub name: Ident,
+    /// Base language, whose sections are extended by the sections of this definition
+    pub extends: Option<Ident>,
+    /// Sections of the base language to remove
+    pub without: Vec<Ident>,
     pub delimiters: Vec<(String, String)>,
+    /// Delimiters opening and closing with the same pattern, alternating between the two, i.e.
+    /// `"|" => line` for closure parameters
+    pub symmetrics: Vec<(String, Limit)>,
+    /// Angle brackets of generic arguments, i.e. `"<" => ">"` for `Vec<T>`. Only opening after
+    /// an identifier or `::`, and dropped when not closed on the same line
+    pub generics: Vec<(String, String)>,
     pub line_comments: Vec<String>,
     pub block_comments: Vec<(String, String)>,
     pub strings: Vec<String>,
     pub chars: Vec<String>,
     pub block_strings: Vec<(String, String)>,
+    /// Regex literals on a single line, i.e. `/a+/` and `%r{a+}`
+    pub regexes: Vec<(String, String)>,
+    /// Prefixes of quote-like operators with the delimiter chosen at the use site, and the
+    /// number of parts, i.e. `"q" => 1` for `q{..}` and `"s" => 2` for `s/../../` or `s{..}{..}`
+    pub quote_likes: Vec<(String, usize)>,
+    /// Line-based pairs, i.e. `"#if" => "#endif"`. Only the first byte is a token, the rest of
+    /// the marker is lexed from the line
+    pub markers: Vec<(String, String)>,
+    /// Separators between the branches of a marker pair, by closing marker, i.e.
+    /// `"#else" => "#endif"`
+    pub middles: Vec<(String, String)>,
+    /// Region markers inside of line comments, i.e. `"#region" => "#endregion"` for
+    /// `// #region`. Lexed from the comment, so they don't add tokens
+    pub regions: Vec<(String, String)>,
+    /// Sections continuing onto the next line when the line ends with a backslash, either
+    /// `string` or `line_comment`
+    pub continuations: Vec<String>,
+    /// Sections in which delimiters are still matched, reported as embedded delimiters, any of
+    /// `string`, `block_string`, `line_comment` or `block_comment`
+    pub embedded: Vec<String>,
+    /// Escape rules of strings and block strings, by opening delimiter
+    pub escapes: Vec<(String, Escape)>,
+    /// Anchors of patterns, by section and pattern, written as `"#" @ whitespace`
+    pub anchors: Vec<(String, String, Anchor)>,
+}
+
+impl MatcherDef {
+    pub fn escape(&self, open: &str) -> Escape {
+        self.escapes
+            .iter()
+            .rev()
+            .find(|(escape_open, _)| escape_open == open)
+            .map(|(_, escape)| *escape)
+            .unwrap_or(Escape::Backslash)
+    }
+
+    pub fn anchor(&self, section: &str, pattern: &str) -> Option<Anchor> {
+        self.anchors
+            .iter()
+            .rev()
+            .find(|(anchor_section, anchor_pattern, _)| {
+                anchor_section == section && anchor_pattern == pattern
+            })
+            .map(|(_, _, anchor)| *anchor)
+    }
+}
+
+/// Parses a pattern, optionally followed by an anchor, i.e. `"=begin" @ line_start`
+fn parse_pattern(
+    input: ParseStream,
+    section: &str,
+    anchors: &mut Vec<(String, String, Anchor)>,
+) -> Result<String> {
+    let pattern = input.parse::<LitStr>()?.value();
+    if input.peek(At) {
+        input.parse::<At>()?;
+        let anchor = input.parse::<Ident>()?;
+        let anchor = match anchor.to_string().as_str() {
+            "line_start" => Anchor::LineStart,
+            "indent" => Anchor::Indent,
+            "whitespace" => Anchor::Whitespace,
+            "word" => Anchor::Word,
+            "word_start" => Anchor::WordStart,
+            "outside_number" => Anchor::OutsideNumber,
+            _ => {
+                return Err(syn::Error::new(
+                    anchor.span(),
+                    "Anchor must be one of `line_start`, `indent`, `whitespace`, `word`, `word_start` or `outside_number`",
+                ))
+            }
+        };
+        anchors.push((section.to_string(), pattern.clone(), anchor));
+    }
+    Ok(pattern)
 }
 
 // Parse the incoming macro definition into a MatcherDef struct
@@ -22,39 +210,83 @@ impl Parse for MatcherDef {
         let content;
         braced!(content in input);
 
+        let mut extends = None;
+        let mut without = Vec::new();
         let mut delimiters = Vec::new();
+        let mut symmetrics = Vec::new();
+        let mut generics = Vec::new();
         let mut line_comments = Vec::new();
         let mut block_comments = Vec::new();
         let mut strings = Vec::new();
         let mut chars = Vec::new();
         let mut block_strings = Vec::new();
-
-        fn get_single_char(token: LitStr) -> Result<String> {
-            let value = token.value();
-            if value.len() != 1 {
-                Err(syn::Error::new(
-                    token.span(),
-                    "Delimiter must be a single character",
-                ))
-            } else {
-                Ok(value)
-            }
-        }
+        let mut regexes = Vec::new();
+        let mut quote_likes = Vec::new();
+        let mut markers = Vec::new();
+        let mut middles = Vec::new();
+        let mut regions = Vec::new();
+        let mut continuations = Vec::new();
+        let mut embedded = Vec::new();
+        let mut escapes = Vec::new();
+        let mut anchors = Vec::new();
 
         // Parse each section
         while !content.is_empty() {
             let section_name = content.parse::<Ident>()?;
             content.parse::<Colon>()?;
 
+            if section_name == "extends" {
+                extends = Some(content.parse::<Ident>()?);
+                if !content.is_empty() {
+                    content.parse::<Comma>()?;
+                }
+                continue;
+            }
+
             let section_content;
             bracketed!(section_content in content);
 
             match section_name.to_string().as_str() {
+                "escape" => {
+                    while !section_content.is_empty() {
+                        let open = section_content.parse::<LitStr>()?;
+                        section_content.parse::<FatArrow>()?;
+                        let escape = section_content.parse::<Ident>()?;
+                        let escape = match escape.to_string().as_str() {
+                            "none" => Escape::None,
+                            "backslash" => Escape::Backslash,
+                            "doubled" => Escape::Doubled,
+                            _ => {
+                                return Err(syn::Error::new(
+                                    escape.span(),
+                                    "Escape must be one of `none`, `backslash` or `doubled`",
+                                ))
+                            }
+                        };
+                        escapes.push((open, escape));
+
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
+                "without" => {
+                    while !section_content.is_empty() {
+                        let section = section_content.parse::<Ident>()?;
+                        if !SECTIONS.contains(&section.to_string().as_str()) {
+                            return Err(syn::Error::new(section.span(), "Unknown section name"));
+                        }
+                        without.push(section);
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
                 "delimiters" => {
                     while !section_content.is_empty() {
-                        let open = get_single_char(section_content.parse::<LitStr>()?)?;
+                        let open = parse_pattern(&section_content, "delimiters", &mut anchors)?;
                         section_content.parse::<FatArrow>()?;
-                        let close = get_single_char(section_content.parse::<LitStr>()?)?;
+                        let close = parse_pattern(&section_content, "delimiters", &mut anchors)?;
                         delimiters.push((open, close));
 
                         if !section_content.is_empty() {
@@ -62,19 +294,67 @@ impl Parse for MatcherDef {
                         }
                     }
                 }
-                "line_comment" => {
+                "symmetric" => {
                     while !section_content.is_empty() {
-                        line_comments.push(section_content.parse::<LitStr>()?.value());
+                        let delim = parse_pattern(&section_content, "symmetric", &mut anchors)?;
+                        let mut limit = Limit::None;
+                        if section_content.peek(FatArrow) {
+                            section_content.parse::<FatArrow>()?;
+                            let ident = section_content.parse::<Ident>()?;
+                            limit = match ident.to_string().as_str() {
+                                "none" => Limit::None,
+                                "line" => Limit::Line,
+                                _ => {
+                                    return Err(syn::Error::new(
+                                        ident.span(),
+                                        "Limit must be one of `none` or `line`",
+                                    ))
+                                }
+                            };
+                        }
+                        symmetrics.push((delim, limit));
+
                         if !section_content.is_empty() {
                             section_content.parse::<Comma>()?;
                         }
                     }
                 }
-                "block_comment" => {
+                "generic" => {
                     while !section_content.is_empty() {
+                        let span = section_content.span();
                         let open = section_content.parse::<LitStr>()?.value();
                         section_content.parse::<FatArrow>()?;
                         let close = section_content.parse::<LitStr>()?.value();
+                        if open.len() != 1 || close.len() != 1 {
+                            return Err(syn::Error::new(
+                                span,
+                                "Generic delimiters must be single characters",
+                            ));
+                        }
+                        generics.push((open, close));
+
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
+                "line_comment" => {
+                    while !section_content.is_empty() {
+                        line_comments.push(parse_pattern(
+                            &section_content,
+                            "line_comment",
+                            &mut anchors,
+                        )?);
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
+                "block_comment" => {
+                    while !section_content.is_empty() {
+                        let open = parse_pattern(&section_content, "block_comment", &mut anchors)?;
+                        section_content.parse::<FatArrow>()?;
+                        let close = parse_pattern(&section_content, "block_comment", &mut anchors)?;
                         block_comments.push((open, close));
 
                         if !section_content.is_empty() {
@@ -84,7 +364,7 @@ impl Parse for MatcherDef {
                 }
                 "string" => {
                     while !section_content.is_empty() {
-                        strings.push(section_content.parse::<LitStr>()?.value());
+                        strings.push(parse_pattern(&section_content, "string", &mut anchors)?);
                         if !section_content.is_empty() {
                             section_content.parse::<Comma>()?;
                         }
@@ -92,7 +372,14 @@ impl Parse for MatcherDef {
                 }
                 "char" => {
                     while !section_content.is_empty() {
-                        let delim = get_single_char(section_content.parse::<LitStr>()?)?;
+                        let span = section_content.span();
+                        let delim = parse_pattern(&section_content, "char", &mut anchors)?;
+                        if delim.len() != 1 {
+                            return Err(syn::Error::new(
+                                span,
+                                "Char delimiter must be a single character",
+                            ));
+                        }
                         chars.push(delim);
                         if !section_content.is_empty() {
                             section_content.parse::<Comma>()?;
@@ -101,10 +388,110 @@ impl Parse for MatcherDef {
                 }
                 "block_string" => {
                     while !section_content.is_empty() {
+                        let open = parse_pattern(&section_content, "block_string", &mut anchors)?;
+                        section_content.parse::<FatArrow>()?;
+                        let close = parse_pattern(&section_content, "block_string", &mut anchors)?;
+                        block_strings.push((open, close));
+
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
+                "regex" => {
+                    while !section_content.is_empty() {
+                        let open = parse_pattern(&section_content, "regex", &mut anchors)?;
+                        section_content.parse::<FatArrow>()?;
+                        let close = section_content.parse::<LitStr>()?.value();
+                        regexes.push((open, close));
+
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
+                "quote_like" => {
+                    while !section_content.is_empty() {
+                        let prefix = section_content.parse::<LitStr>()?.value();
+                        section_content.parse::<FatArrow>()?;
+                        let parts = section_content.parse::<LitInt>()?;
+                        let parts_value = parts.base10_parse::<usize>()?;
+                        if !(1..=2).contains(&parts_value) {
+                            return Err(syn::Error::new(
+                                parts.span(),
+                                "Quote-like operators must have 1 or 2 parts",
+                            ));
+                        }
+                        quote_likes.push((prefix, parts_value));
+
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
+                "region" => {
+                    while !section_content.is_empty() {
+                        let span = section_content.span();
                         let open = section_content.parse::<LitStr>()?.value();
                         section_content.parse::<FatArrow>()?;
                         let close = section_content.parse::<LitStr>()?.value();
-                        block_strings.push((open, close));
+                        if open.is_empty() || close.is_empty() {
+                            return Err(syn::Error::new(span, "Regions must not be empty"));
+                        }
+                        regions.push((open, close));
+
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
+                "continuation" => {
+                    while !section_content.is_empty() {
+                        let section = section_content.parse::<Ident>()?;
+                        if section != "string" && section != "line_comment" {
+                            return Err(syn::Error::new(
+                                section.span(),
+                                "Continuation must be one of `string` or `line_comment`",
+                            ));
+                        }
+                        continuations.push(section.to_string());
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
+                "embedded" => {
+                    while !section_content.is_empty() {
+                        let section = section_content.parse::<Ident>()?;
+                        if !["string", "block_string", "line_comment", "block_comment"]
+                            .contains(&section.to_string().as_str())
+                        {
+                            return Err(syn::Error::new(
+                                section.span(),
+                                "Embedded must be one of `string`, `block_string`, `line_comment` or `block_comment`",
+                            ));
+                        }
+                        embedded.push(section.to_string());
+                        if !section_content.is_empty() {
+                            section_content.parse::<Comma>()?;
+                        }
+                    }
+                }
+                "marker" | "middle" => {
+                    let section = section_name.to_string();
+                    while !section_content.is_empty() {
+                        let span = section_content.span();
+                        let open = parse_pattern(&section_content, &section, &mut anchors)?;
+                        section_content.parse::<FatArrow>()?;
+                        let close = parse_pattern(&section_content, &section, &mut anchors)?;
+                        let is_valid = |marker: &str| !marker.is_empty() && marker.is_ascii();
+                        if !is_valid(&open) || !is_valid(&close) {
+                            return Err(syn::Error::new(span, "Markers must be non-empty ASCII"));
+                        }
+                        match section.as_str() {
+                            "marker" => markers.push((open, close)),
+                            _ => middles.push((open, close)),
+                        }
 
                         if !section_content.is_empty() {
                             section_content.parse::<Comma>()?;
@@ -119,14 +506,54 @@ impl Parse for MatcherDef {
             }
         }
 
+        if extends.is_none() && !without.is_empty() {
+            return Err(syn::Error::new(
+                without[0].span(),
+                "`without` requires a base language with `extends`",
+            ));
+        }
+
+        // Escapes must refer to strings or block strings of this definition, except when
+        // extending a language, in which case they're validated after merging
+        let escapes = escapes
+            .into_iter()
+            .map(|(open, escape)| {
+                let value = open.value();
+                let is_string = strings.contains(&value)
+                    || block_strings
+                        .iter()
+                        .any(|(block_open, _)| *block_open == value);
+                if !is_string && extends.is_none() {
+                    return Err(syn::Error::new(
+                        open.span(),
+                        "Escape must refer to the opening delimiter of a string or block string",
+                    ));
+                }
+                Ok((value, escape))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
         Ok(MatcherDef {
             name,
+            extends,
+            without,
             delimiters,
+            symmetrics,
+            generics,
             line_comments,
             block_comments,
             strings,
             chars,
             block_strings,
+            regexes,
+            quote_likes,
+            markers,
+            middles,
+            regions,
+            continuations,
+            embedded,
+            escapes,
+            anchors,
         })
     }
 }
@@ -145,6 +572,18 @@ pub fn collect_tokens(def: &MatcherDef) -> Vec<u8> {
         }
     }
 
+    for (delim, _) in &def.symmetrics {
+        for c in delim.bytes() {
+            all_tokens.insert(c);
+        }
+    }
+
+    for (open, close) in &def.generics {
+        for c in open.bytes().chain(close.bytes()) {
+            all_tokens.insert(c);
+        }
+    }
+
     for comment in &def.line_comments {
         for c in comment.bytes() {
             all_tokens.insert(c);
@@ -175,12 +614,36 @@ pub fn collect_tokens(def: &MatcherDef) -> Vec<u8> {
         }
     }
 
+    // The closing delimiter of regexes is found by lexing the line
+    for (open, _) in &def.regexes {
+        for c in open.bytes() {
+            all_tokens.insert(c);
+        }
+    }
+
+    // The prefix of quote-like operators is lexed from the line before the delimiter
+    if !def.quote_likes.is_empty() {
+        for (open, close) in QUOTE_LIKE_DELIMITERS {
+            for c in open.bytes().chain(close.bytes()) {
+                all_tokens.insert(c);
+            }
+        }
+    }
+
     for s in &def.chars {
         for c in s.bytes() {
             all_tokens.insert(c);
         }
     }
 
+    // The rest of the marker is lexed from the line
+    for (marker, _) in def.markers.iter().chain(&def.middles) {
+        all_tokens.insert(marker.as_bytes()[0]);
+    }
+    for (_, close) in &def.markers {
+        all_tokens.insert(close.as_bytes()[0]);
+    }
+
     // Convert to sorted vector
     let mut tokens_vec: Vec<u8> = all_tokens.into_iter().collect();
     tokens_vec.sort();
@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+use syn::{Ident, Item, Result};
+
+use crate::config::MatcherDef;
+
+/// Directory holding the `define_matcher!` invocations, relative to the crate being compiled
+const LANGUAGES_DIR: &str = "src/parser/languages";
+
+impl MatcherDef {
+    /// Merges the sections of the base language (and its own base languages) into this
+    /// definition. Returns the files holding the base definitions, so that they may be tracked
+    /// for changes
+    pub fn resolve_extends(mut self) -> Result<(Self, Vec<PathBuf>)> {
+        let Some(extends) = self.extends.clone() else {
+            return Ok((self, vec![]));
+        };
+
+        let (base, path) = find_definition(&extends)?;
+        let (mut base, mut paths) = base.resolve_extends()?;
+        paths.push(path);
+
+        for section in &self.without {
+            let section = section.to_string();
+            base.anchors
+                .retain(|(anchor_section, _, _)| *anchor_section != section);
+            match section.as_str() {
+                "delimiters" => base.delimiters.clear(),
+                "symmetric" => base.symmetrics.clear(),
+                "generic" => base.generics.clear(),
+                "line_comment" => base.line_comments.clear(),
+                "block_comment" => base.block_comments.clear(),
+                "string" => base.strings.clear(),
+                "char" => base.chars.clear(),
+                "block_string" => base.block_strings.clear(),
+                "regex" => base.regexes.clear(),
+                "quote_like" => base.quote_likes.clear(),
+                "marker" => base.markers.clear(),
+                "middle" => base.middles.clear(),
+                "region" => base.regions.clear(),
+                "continuation" => base.continuations.clear(),
+                "embedded" => base.embedded.clear(),
+                "escape" => base.escapes.clear(),
+                _ => unreachable!("validated while parsing"),
+            }
+        }
+
+        base.delimiters.append(&mut self.delimiters);
+        base.symmetrics.append(&mut self.symmetrics);
+        base.generics.append(&mut self.generics);
+        base.line_comments.append(&mut self.line_comments);
+        base.block_comments.append(&mut self.block_comments);
+        base.strings.append(&mut self.strings);
+        base.chars.append(&mut self.chars);
+        base.block_strings.append(&mut self.block_strings);
+        base.regexes.append(&mut self.regexes);
+        base.quote_likes.append(&mut self.quote_likes);
+        base.markers.append(&mut self.markers);
+        base.middles.append(&mut self.middles);
+        base.regions.append(&mut self.regions);
+        base.continuations.append(&mut self.continuations);
+        base.embedded.append(&mut self.embedded);
+        base.escapes.append(&mut self.escapes);
+        base.anchors.append(&mut self.anchors);
+
+        self.delimiters = base.delimiters;
+        self.symmetrics = base.symmetrics;
+        self.generics = base.generics;
+        self.line_comments = base.line_comments;
+        self.block_comments = base.block_comments;
+        self.strings = base.strings;
+        self.chars = base.chars;
+        self.block_strings = base.block_strings;
+        self.regexes = base.regexes;
+        self.quote_likes = base.quote_likes;
+        self.markers = base.markers;
+        self.middles = base.middles;
+        self.regions = base.regions;
+        self.continuations = base.continuations;
+        self.embedded = base.embedded;
+        self.escapes = base.escapes;
+        self.anchors = base.anchors;
+
+        Ok((self, paths))
+    }
+}
+
+/// Proc macros can't share state between invocations, so the base language is found by parsing
+/// the `define_matcher!` invocations in the languages directory
+fn find_definition(name: &Ident) -> Result<(MatcherDef, PathBuf)> {
+    let error = |message: String| syn::Error::new(name.span(), message);
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| error("CARGO_MANIFEST_DIR is not set".to_string()))?;
+    let dir = PathBuf::from(manifest_dir).join(LANGUAGES_DIR);
+    let entries = std::fs::read_dir(&dir)
+        .map_err(|err| error(format!("failed to read {}: {}", dir.display(), err)))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+
+        let source = std::fs::read_to_string(&path)
+            .map_err(|err| error(format!("failed to read {}: {}", path.display(), err)))?;
+        let Ok(file) = syn::parse_file(&source) else {
+            continue;
+        };
+
+        for item in file.items {
+            let Item::Macro(item) = item else {
+                continue;
+            };
+            if !item.mac.path.is_ident("define_matcher") {
+                continue;
+            }
+            if let Ok(def) = item.mac.parse_body::<MatcherDef>() {
+                if def.name == *name {
+                    return Ok((def, path));
+                }
+            }
+        }
+    }
+
+    Err(error(format!(
+        "no `define_matcher!({name} {{ .. }})` found in {LANGUAGES_DIR}"
+    )))
+}
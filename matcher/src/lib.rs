@@ -1,18 +1,26 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::parse_macro_input;
 
 mod config;
+mod extends;
 mod lookahead;
 mod matcher;
 
-use config::{collect_tokens, MatcherDef};
+use config::{collect_tokens, Escape, Limit, MatcherDef, QUOTE_LIKE_DELIMITERS};
 use lookahead::{calculate_max_lookahead, generate_lookahead_extractors};
 use matcher::{create_match_header, MatchArm};
 
 #[proc_macro]
 pub fn define_matcher(input: TokenStream) -> TokenStream {
     let def = parse_macro_input!(input as MatcherDef);
+    let (def, base_files) = match def.resolve_extends() {
+        Ok(resolved) => resolved,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if let Err(err) = check_escapes(&def) {
+        return err.to_compile_error().into();
+    }
     let max_lookahead = calculate_max_lookahead(&def);
     let all_tokens = collect_tokens(&def);
     let token_literals = all_tokens.iter().map(|&t| quote! { #t });
@@ -21,29 +29,90 @@ pub fn define_matcher(input: TokenStream) -> TokenStream {
     // Generate match arms for all patterns
     let mut match_arms = Vec::new();
 
-    // Order matters, we want to prioritize:
+    // Line-limited symmetric delimiters and generic angle brackets left open are dropped before
+    // closing a delimiter below them on the stack
+    let same_line = def
+        .symmetrics
+        .iter()
+        .filter(|(_, limit)| *limit == Limit::Line)
+        .map(|(delim, _)| delim)
+        .chain(def.generics.iter().map(|(_, close)| close))
+        .collect::<Vec<_>>();
+    let unwind = |close: &str| {
+        (!same_line.is_empty()).then(|| {
+            quote! { unwind_symmetric(matches, stack, #close, self.same_line_delimiters()); }
+        })
+    };
+
+    // Order matters, the arms are sorted so that the longest pattern wins, and then by section:
+    // - markers, which are lexed from the line
     // - block strings and block comments
-    // - line comments, strings, and chars
-    // - finally, delimiters
+    // - line comments, strings, regexes, quote-like operators and chars
+    // - finally, delimiters, symmetric delimiters and generic angle brackets
+
+    // 1. Marker patterns, lexed from the line since only their first byte is a token. They don't
+    // touch the stack, the heights of markers are calculated by the buffer
+    let mut closes = vec![];
+    for (open, close) in &def.markers {
+        let first_byte = open[..1].to_string();
+        let open_arm = MatchArm::builder(first_byte, max_lookahead)
+            .lexed(open, quote! { marker_at(line, token.col, #open) })
+            .anchor(def.anchor("marker", open))
+            .body(quote! {
+                matches.push(Match::new(Kind::Opening, Token::Marker(#open, #close), token.col));
+                skip_to_col(tokens, token.col + #open.len());
+                State::Normal
+            });
+        match_arms.push(("marker", open_arm));
 
-    // 1. Block comment patterns
+        // Opening markers may share the same closing marker, i.e. `#if` and `#ifdef`
+        if closes.contains(&close) {
+            continue;
+        }
+        closes.push(close);
+        let close_arm = MatchArm::builder(close[..1].to_string(), max_lookahead)
+            .lexed(close, quote! { marker_at(line, token.col, #close) })
+            .anchor(def.anchor("marker", close))
+            .body(quote! {
+                matches.push(Match::new(Kind::Closing, Token::Marker(#close, #close), token.col));
+                skip_to_col(tokens, token.col + #close.len());
+                State::Normal
+            });
+        match_arms.push(("marker", close_arm));
+    }
+    for (middle, close) in &def.middles {
+        let arm = MatchArm::builder(middle[..1].to_string(), max_lookahead)
+            .lexed(middle, quote! { marker_at(line, token.col, #middle) })
+            .anchor(def.anchor("middle", middle))
+            .body(quote! {
+                matches.push(Match::new(Kind::Middle, Token::Marker(#middle, #close), token.col));
+                skip_to_col(tokens, token.col + #middle.len());
+                State::Normal
+            });
+        match_arms.push(("middle", arm));
+    }
+
+    // 2. Block comment patterns
     for (open, close) in &def.block_comments {
-        let open_arm = MatchArm::builder(open.to_string(), max_lookahead).body(quote! {
-            matches.push(Match::new(
-                Kind::Opening,
-                Token::BlockComment(#open, #close),
-                token.col,
-            ));
-            // Skip tokens based on length of pattern
-            for _ in 1..#open.len() {
-                tokens.next();
-            }
-            State::InBlockComment(#open)
-        });
-        match_arms.push(open_arm.build());
+        let open_arm = MatchArm::builder(open.to_string(), max_lookahead)
+            .anchor(def.anchor("block_comment", open))
+            .body(quote! {
+                matches.push(Match::new(
+                    Kind::Opening,
+                    Token::BlockComment(#open, #close),
+                    token.col,
+                ));
+                // Skip tokens based on length of pattern
+                for _ in 1..#open.len() {
+                    tokens.next();
+                }
+                State::InBlockComment(#open)
+            });
+        match_arms.push(("block_comment", open_arm));
 
         let close_arm = MatchArm::builder(close.to_string(), max_lookahead)
             .input_state(quote! { State::InBlockComment(#open) })
+            .anchor(def.anchor("block_comment", close))
             .body(quote! {
                 matches.push(Match::new(
                     Kind::Closing,
@@ -56,28 +125,31 @@ pub fn define_matcher(input: TokenStream) -> TokenStream {
                 }
                 State::Normal
             });
-        match_arms.push(close_arm.build());
+        match_arms.push(("block_comment", close_arm));
     }
 
-    // 2. Block string patterns
+    // 3. Block string patterns
     for (open, close) in &def.block_strings {
-        let open_arm = MatchArm::builder(open.to_string(), max_lookahead).body(quote! {
-            matches.push(Match::new(
-                Kind::Opening,
-                Token::BlockString(#open, #close),
-                token.col,
-            ));
-            // Skip tokens based on length of pattern
-            for _ in 1..#open.len() {
-                tokens.next();
-            }
-            State::InBlockString(#open)
-        });
-        match_arms.push(open_arm.build());
+        let open_arm = MatchArm::builder(open.to_string(), max_lookahead)
+            .anchor(def.anchor("block_string", open))
+            .body(quote! {
+                matches.push(Match::new(
+                    Kind::Opening,
+                    Token::BlockString(#open, #close),
+                    token.col,
+                ));
+                // Skip tokens based on length of pattern
+                for _ in 1..#open.len() {
+                    tokens.next();
+                }
+                State::InBlockString(#open, 0)
+            });
+        match_arms.push(("block_string", open_arm));
 
+        let input_state = quote! { State::InBlockString(#open, _) };
         let close_arm = MatchArm::builder(close.to_string(), max_lookahead)
-            .ignore_escaped()
-            .input_state(quote! { State::InBlockString(#open) })
+            .input_state(input_state.clone())
+            .anchor(def.anchor("block_string", close))
             .body(quote! {
                 matches.push(Match::new(
                     Kind::Closing,
@@ -90,41 +162,64 @@ pub fn define_matcher(input: TokenStream) -> TokenStream {
                 }
                 State::Normal
             });
-        match_arms.push(close_arm.build());
+        push_close_arm(
+            &mut match_arms,
+            "block_string",
+            close_arm,
+            def.escape(open),
+            close,
+            input_state,
+            max_lookahead,
+        );
     }
 
-    // 3. Line comment patterns
-    for comment in &def.line_comments {
-        let arm = MatchArm::builder(comment.to_string(), max_lookahead).body(quote! {
-            matches.push(Match::line_comment(#comment, token.col));
-            // Skip tokens based on length of pattern
-            for _ in 1..#comment.len() {
-                tokens.next();
+    // 4. Line comment patterns, with the region markers inside of the comment
+    let (region_open, region_close): (Vec<_>, Vec<_>) = def.regions.iter().cloned().unzip();
+    let find_region = (!def.regions.is_empty()).then(|| {
+        quote! {
+            let regions = &[#((#region_open, #region_close)),*];
+            if let Some(region) = region_in_comment(line, token.col, regions) {
+                matches.push(region);
             }
-            State::InLineComment
-        });
+        }
+    });
+    for comment in &def.line_comments {
+        let arm = MatchArm::builder(comment.to_string(), max_lookahead)
+            .anchor(def.anchor("line_comment", comment))
+            .body(quote! {
+                matches.push(Match::line_comment(#comment, token.col));
+                #find_region
+                // Skip tokens based on length of pattern
+                for _ in 1..#comment.len() {
+                    tokens.next();
+                }
+                State::InLineComment
+            });
         // TODO: skip tokens based on length of pattern
-        match_arms.push(arm.build());
+        match_arms.push(("line_comment", arm));
     }
 
-    // 4. String patterns
+    // 5. String patterns
     for delim in &def.strings {
         // Opening string
-        let open_arm = MatchArm::builder(delim.to_string(), max_lookahead).body(quote! {
-            matches.push(Match::new(Kind::Opening, Token::String(#delim), token.col));
-            // Skip tokens based on length of pattern
-            for _ in 1..#delim.len() {
-                tokens.next();
-            }
-            State::InString(#delim)
-        });
+        let open_arm = MatchArm::builder(delim.to_string(), max_lookahead)
+            .anchor(def.anchor("string", delim))
+            .body(quote! {
+                matches.push(Match::new(Kind::Opening, Token::String(#delim), token.col));
+                // Skip tokens based on length of pattern
+                for _ in 1..#delim.len() {
+                    tokens.next();
+                }
+                State::InString(#delim)
+            });
         // TODO: skip tokens based on length of pattern
-        match_arms.push(open_arm.build());
+        match_arms.push(("string", open_arm));
 
         // Closing string
+        let input_state = quote! { State::InString(#delim) };
         let close_arm = MatchArm::builder(delim.to_string(), max_lookahead)
-            .ignore_escaped()
-            .input_state(quote! { State::InString(#delim) })
+            .input_state(input_state.clone())
+            .anchor(def.anchor("string", delim))
             .body(quote! {
                 matches.push(Match::new(Kind::Closing, Token::String(#delim), token.col));
                 // Skip tokens based on length of pattern
@@ -133,63 +228,312 @@ pub fn define_matcher(input: TokenStream) -> TokenStream {
                 }
                 State::Normal
             });
-        // TODO: skip tokens based on length of pattern
-        match_arms.push(close_arm.build());
+        push_close_arm(
+            &mut match_arms,
+            "string",
+            close_arm,
+            def.escape(delim),
+            delim,
+            input_state,
+            max_lookahead,
+        );
     }
 
-    // 5. Character literal patterns
-    for delim in &def.chars {
-        // TODO: handle escaped
-        let delim_byte = delim.as_bytes()[0];
-        let arm = MatchArm::builder(delim.to_string(), max_lookahead)
-            .non_adjacent()
-            .if_condition(quote! { token_1_byte == #delim_byte && (token_1_distance == 1 || token_1_distance == 2) })
+    // 6. Regex literal patterns, lexed from the line like char literals. They're reported as
+    // block strings closed on the same line
+    for (open, close) in &def.regexes {
+        let arm = MatchArm::builder(open.to_string(), max_lookahead)
+            .anchor(def.anchor("regex", open))
+            .if_condition(quote! { regex_literal_len(line, token.col, #open, #close).is_some() })
             .body(quote! {
-                matches.push(Match::new(Kind::Opening, Token::String(#delim), token.col));
-                matches.push(Match::new(Kind::Closing, Token::String(#delim), token.col + token_1_distance));
-                tokens.next(); // Skip next token
+                let len = regex_literal_len(line, token.col, #open, #close).unwrap();
+                matches.push(Match::new(Kind::Opening, Token::BlockString(#open, #close), token.col));
+                matches.push(Match::new(
+                    Kind::Closing,
+                    Token::BlockString(#open, #close),
+                    token.col + len - #close.len(),
+                ));
+                skip_to_col(tokens, token.col + len);
                 State::Normal
             });
-        match_arms.push(arm.build());
+        match_arms.push(("regex", arm));
+    }
+
+    // 7. Quote-like operators, reported as block strings. They're matched on their delimiter,
+    // with the prefix lexed from the line, so that the prefix isn't a token, i.e. `q` in `q{a}`.
+    // Nested pairs of bracketed delimiters are counted in the state, i.e. `q{a{b}}`
+    for (prefix, parts) in &def.quote_likes {
+        for (open_delim, close_delim) in QUOTE_LIKE_DELIMITERS {
+            let open = format!("{prefix}{open_delim}");
+            let open_arm = MatchArm::builder(open_delim.to_string(), max_lookahead)
+                .lexed(
+                    &open,
+                    quote! { line[..token.col].ends_with(#prefix.as_bytes()) },
+                )
+                .if_condition(quote! { quote_like_start(line, token.col - #prefix.len()) })
+                .body(quote! {
+                    matches.push(Match::new(
+                        Kind::Opening,
+                        Token::BlockString(#open, #close_delim),
+                        token.col - #prefix.len(),
+                    ));
+                    // Skip tokens based on length of pattern
+                    for _ in 1..#open_delim.len() {
+                        tokens.next();
+                    }
+                    State::InBlockString(#open, 0)
+                });
+            match_arms.push(("quote_like", open_arm));
+
+            let close_body = quote! {
+                matches.push(Match::new(
+                    Kind::Closing,
+                    Token::BlockString(#open, #close_delim),
+                    token.col,
+                ));
+                // Skip tokens based on length of pattern
+                for _ in 1..#close_delim.len() {
+                    tokens.next();
+                }
+                State::Normal
+            };
+            let input_state = quote! { State::InBlockString(#open, _) };
+            if open_delim != close_delim {
+                push_nested_arms(
+                    &mut match_arms,
+                    &open,
+                    open_delim,
+                    close_delim,
+                    max_lookahead,
+                );
+
+                // With two parts, wait for the second bracketed part, i.e. `s{a} {b}`
+                let between = format!("{open}{close_delim}");
+                let wait_for_second_part = (*parts == 2).then(|| {
+                    quote! {
+                        if quote_like_second_part(line, token.col + #close_delim.len()) {
+                            return State::InBlockString(#between, 0);
+                        }
+                    }
+                });
+                let close_arm = MatchArm::builder(close_delim.to_string(), max_lookahead)
+                    .input_state(quote! { State::InBlockString(#open, 0) })
+                    .ignore_escaped()
+                    .body(quote! {
+                        #wait_for_second_part
+                        #close_body
+                    });
+                match_arms.push(("quote_like", close_arm));
+
+                if *parts == 2 {
+                    // The second part may use other brackets, i.e. `s{a}(b)`
+                    let nested_delims = QUOTE_LIKE_DELIMITERS
+                        .iter()
+                        .filter(|(open, close)| open != close);
+                    for (second_open, second_close) in nested_delims {
+                        let second = format!("{between}{second_open}");
+                        let open_arm = MatchArm::builder(second_open.to_string(), max_lookahead)
+                            .input_state(quote! { State::InBlockString(#between, _) })
+                            .body(quote! {
+                                // Skip tokens based on length of pattern
+                                for _ in 1..#second_open.len() {
+                                    tokens.next();
+                                }
+                                State::InBlockString(#second, 0)
+                            });
+                        match_arms.push(("quote_like", open_arm));
 
+                        push_nested_arms(
+                            &mut match_arms,
+                            &second,
+                            second_open,
+                            second_close,
+                            max_lookahead,
+                        );
+                        let close_arm = MatchArm::builder(second_close.to_string(), max_lookahead)
+                            .input_state(quote! { State::InBlockString(#second, 0) })
+                            .ignore_escaped()
+                            .body(close_body.clone());
+                        match_arms.push(("quote_like", close_arm));
+                    }
+                }
+            } else if *parts == 2 {
+                // The closing delimiter of the first part opens the second part, i.e. `s/a/b/`
+                let middle = format!("{open}{close_delim}");
+                let middle_arm = MatchArm::builder(close_delim.to_string(), max_lookahead)
+                    .input_state(input_state)
+                    .ignore_escaped()
+                    .body(quote! {
+                        // Skip tokens based on length of pattern
+                        for _ in 1..#close_delim.len() {
+                            tokens.next();
+                        }
+                        State::InBlockString(#middle, 0)
+                    });
+                match_arms.push(("quote_like", middle_arm));
+
+                let close_arm = MatchArm::builder(close_delim.to_string(), max_lookahead)
+                    .input_state(quote! { State::InBlockString(#middle, _) })
+                    .ignore_escaped()
+                    .body(close_body);
+                match_arms.push(("quote_like", close_arm));
+            } else {
+                let close_arm = MatchArm::builder(close_delim.to_string(), max_lookahead)
+                    .input_state(input_state)
+                    .ignore_escaped()
+                    .body(close_body);
+                match_arms.push(("quote_like", close_arm));
+            }
+        }
+    }
+
+    // 8. Character literal patterns, lexed from the line since escape sequences and the
+    // characters inside of the literal may not be tokens
+    for delim in &def.chars {
+        let delim_byte = delim.as_bytes()[0];
         let arm = MatchArm::builder(delim.to_string(), max_lookahead)
-            .non_adjacent()
-            .if_condition(quote! { token_2_byte == #delim_byte && token_2_distance == 2 })
+            .anchor(def.anchor("char", delim))
+            .if_condition(quote! { char_literal_len(line, token.col, #delim_byte).is_some() })
             .body(quote! {
+                let len = char_literal_len(line, token.col, #delim_byte).unwrap();
                 matches.push(Match::new(Kind::Opening, Token::String(#delim), token.col));
-                matches.push(Match::new(Kind::Closing, Token::String(#delim), token.col + token_2_distance));
-                tokens.next(); // Skip 2 tokens
-                tokens.next();
+                matches.push(Match::new(Kind::Closing, Token::String(#delim), token.col + len - 1));
+                skip_to_col(tokens, token.col + len);
                 State::Normal
             });
-        match_arms.push(arm.build());
+        match_arms.push(("char", arm));
     }
 
-    // 6. Delimiter patterns
+    // 9. Delimiter patterns
     for (open, close) in &def.delimiters {
-        let close_byte = close.as_bytes()[0];
-
         // Opening delimiter
-        let open_arm = MatchArm::builder(open.to_string(), max_lookahead).body(quote! {
+        let open_arm = MatchArm::builder(open.to_string(), max_lookahead)
+            .anchor(def.anchor("delimiters", open))
+            .body(quote! {
             matches.push(Match::new_with_stack(Kind::Opening, Token::Delimiter(#open, #close), token.col, stack.len()));
-            stack.push(#close_byte);
+            stack.push(#close);
+            // Skip tokens based on length of pattern
+            for _ in 1..#open.len() {
+                tokens.next();
+            }
             State::Normal
         });
-        match_arms.push(open_arm.build());
+        match_arms.push(("delimiters", open_arm));
 
         // Closing delimiter
-        let close_arm = MatchArm::builder(close.to_string(), max_lookahead).body(quote! {
-            if let Some(closing) = stack.last() {
-                if token.byte == *closing {
-                    stack.pop();
-                }
+        let unwind = unwind(close);
+        let close_arm = MatchArm::builder(close.to_string(), max_lookahead)
+            .anchor(def.anchor("delimiters", close))
+            .body(quote! {
+            #unwind
+            if stack.last() == Some(&#close) {
+                stack.pop();
             }
             matches.push(Match::new_with_stack(Kind::Closing, Token::Delimiter(#open, #close), token.col, stack.len()));
+            // Skip tokens based on length of pattern
+            for _ in 1..#close.len() {
+                tokens.next();
+            }
             State::Normal
         });
-        match_arms.push(close_arm.build());
+        match_arms.push(("delimiters", close_arm));
+    }
+
+    // 10. Symmetric delimiter patterns, closing when open at the top of the stack
+    for (delim, _) in &def.symmetrics {
+        let unwind = unwind(delim);
+        let arm = MatchArm::builder(delim.to_string(), max_lookahead)
+            .anchor(def.anchor("symmetric", delim))
+            .body(quote! {
+                #unwind
+                if stack.last() == Some(&#delim) {
+                    stack.pop();
+                    matches.push(Match::new_with_stack(Kind::Closing, Token::Delimiter(#delim, #delim), token.col, stack.len()));
+                } else {
+                    matches.push(Match::new_with_stack(Kind::Opening, Token::Delimiter(#delim, #delim), token.col, stack.len()));
+                    stack.push(#delim);
+                }
+                // Skip tokens based on length of pattern
+                for _ in 1..#delim.len() {
+                    tokens.next();
+                }
+                State::Normal
+            });
+        match_arms.push(("symmetric", arm));
+    }
+
+    // 11. Generic angle brackets, only opening after an identifier and only closing when open at
+    // the top of the stack, otherwise they're operators
+    for (open, close) in &def.generics {
+        let open_arm = MatchArm::builder(open.to_string(), max_lookahead)
+            .if_condition(quote! { opens_generic(line, token.col) })
+            .body(quote! {
+                matches.push(Match::new_with_stack(Kind::Opening, Token::Delimiter(#open, #close), token.col, stack.len()));
+                stack.push(#close);
+                State::Normal
+            });
+        match_arms.push(("generic", open_arm));
+
+        let unwind = unwind(close);
+        let close_arm = MatchArm::builder(close.to_string(), max_lookahead)
+            .if_condition(quote! { closes_generic(line, token.col) })
+            .body(quote! {
+                #unwind
+                if stack.last() == Some(&#close) {
+                    stack.pop();
+                    matches.push(Match::new_with_stack(Kind::Closing, Token::Delimiter(#open, #close), token.col, stack.len()));
+                }
+                State::Normal
+            });
+        match_arms.push(("generic", close_arm));
     }
 
+    // 12. Delimiters embedded in strings and comments, keeping the state. Escaped delimiters are
+    // skipped, i.e. `\(` in regexes
+    let embedded_states = def
+        .embedded
+        .iter()
+        .map(|section| match section.as_str() {
+            "string" => quote! { State::InString(_) },
+            "block_string" => quote! { State::InBlockString(_, _) },
+            "line_comment" => quote! { State::InLineComment },
+            _ => quote! { State::InBlockComment(_) },
+        })
+        .collect::<Vec<_>>();
+    if !embedded_states.is_empty() {
+        let input_state = quote! { #(#embedded_states)|* };
+        for (open, close) in &def.delimiters {
+            for (pattern, kind) in [
+                (open, quote! { Kind::Opening }),
+                (close, quote! { Kind::Closing }),
+            ] {
+                let arm = MatchArm::builder(pattern.to_string(), max_lookahead)
+                    .input_state(input_state.clone())
+                    .ignore_escaped()
+                    .anchor(def.anchor("delimiters", pattern))
+                    .body(quote! {
+                        matches.push(Match::new(#kind, Token::Embedded(#open, #close), token.col));
+                        // Skip tokens based on length of pattern
+                        for _ in 1..#pattern.len() {
+                            tokens.next();
+                        }
+                        state
+                    });
+                match_arms.push(("embedded", arm));
+            }
+        }
+    }
+
+    // Longest match wins, i.e. `$$` takes precedence over `$` and `[|` over `[`
+    match_arms.sort_by_key(|(_, arm)| std::cmp::Reverse(arm.text().len()));
+    if let Err(err) = check_overlapping(&def, &match_arms) {
+        return err.to_compile_error().into();
+    }
+    let mut match_arms = match_arms
+        .into_iter()
+        .map(|(_, arm)| arm.build())
+        .collect::<Vec<_>>();
+
     // Add fallback pattern
     let fallback_arm = quote! { _ => state };
     match_arms.push(fallback_arm);
@@ -203,22 +547,72 @@ pub fn define_matcher(input: TokenStream) -> TokenStream {
     };
 
     let name = &def.name;
+    let spec = generate_spec(&def);
+    let continued_states = def
+        .continuations
+        .iter()
+        .map(|section| match section.as_str() {
+            "string" => quote! { State::InString(_) },
+            _ => quote! { State::InLineComment },
+        })
+        .collect::<Vec<_>>();
+    let continues_line = match continued_states.is_empty() {
+        true => quote! { false },
+        false => quote! { matches!(state, #(#continued_states)|*) },
+    };
+    let name_str = name.to_string();
+    let extends = match &def.extends {
+        Some(extends) => {
+            let extends = extends.to_string();
+            quote! { Some(#extends) }
+        }
+        None => quote! { None },
+    };
+    // Rebuild when the base languages change
+    let base_files = base_files
+        .iter()
+        .map(|path| path.to_string_lossy().to_string());
 
     // Generate the full implementation
     let expanded = quote! {
+        #(const _: &[u8] = include_bytes!(#base_files);)*
+
         pub struct #name;
 
         impl Matcher for #name {
             const TOKENS: &[u8] = &[#(#token_literals),*];
 
+            fn describe(&self) -> LanguageSpec {
+                #spec
+            }
+
+            fn metadata(&self) -> MatcherMetadata {
+                MatcherMetadata {
+                    name: #name_str,
+                    extends: #extends,
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn continues_line(&self, state: State) -> bool {
+                #continues_line
+            }
+
+            fn same_line_delimiters(&self) -> &'static [&'static str] {
+                &[#(#same_line),*]
+            }
+
+            // The line is only used by anchored patterns
+            #[allow(unused_variables)]
             fn call<I>(
                 &mut self,
                 matches: &mut Vec<Match>,
-                stack: &mut Vec<u8>,
+                stack: &mut Vec<&'static str>,
                 tokens: &mut MultiPeek<I>,
                 state: State,
                 token: CharPos,
                 escaped: bool,
+                line: &[u8],
             ) -> State
             where
                 I: Iterator<Item = CharPos>,
@@ -233,3 +627,191 @@ pub fn define_matcher(input: TokenStream) -> TokenStream {
 
     expanded.into()
 }
+
+/// Generates the `LanguageSpec` equivalent of the definition, so that the built-in languages
+/// can be checked against the runtime-defined languages
+fn generate_spec(def: &MatcherDef) -> proc_macro2::TokenStream {
+    let pairs = |pairs: &[(String, String)]| {
+        let (open, close): (Vec<_>, Vec<_>) = pairs.iter().cloned().unzip();
+        quote! { vec![#((#open.to_string(), #close.to_string())),*] }
+    };
+    let list = |list: &[String]| quote! { vec![#(#list.to_string()),*] };
+
+    let delimiters = pairs(&def.delimiters);
+    let generic = pairs(&def.generics);
+    let line_comment = list(&def.line_comments);
+    let block_comment = pairs(&def.block_comments);
+    let string = list(&def.strings);
+    let char = list(&def.chars);
+    let block_string = pairs(&def.block_strings);
+    let regex = pairs(&def.regexes);
+    let marker = pairs(&def.markers);
+    let middle = pairs(&def.middles);
+    let region = pairs(&def.regions);
+    let continuation = list(&def.continuations);
+    let embedded = list(&def.embedded);
+    let (symmetric, symmetric_limit): (Vec<_>, Vec<_>) = def
+        .symmetrics
+        .iter()
+        .map(|(delim, limit)| (delim, format_ident!("{}", limit.name())))
+        .unzip();
+    let (quote_like_prefix, quote_like_parts): (Vec<_>, Vec<_>) =
+        def.quote_likes.iter().cloned().unzip();
+    let anchor = def.anchors.iter().map(|(section, pattern, anchor)| {
+        let anchor = format_ident!("{}", anchor.name());
+        quote! {
+            anchor
+                .entry(#section.to_string())
+                .or_default()
+                .insert(#pattern.to_string(), Anchor::#anchor);
+        }
+    });
+    let (escape_open, escape): (Vec<_>, Vec<_>) = def
+        .escapes
+        .iter()
+        .map(|(open, escape)| (open, format_ident!("{}", escape.name())))
+        .unzip();
+
+    quote! {{
+        #[allow(unused_mut)]
+        let mut anchor = std::collections::BTreeMap::<_, std::collections::BTreeMap<_, _>>::new();
+        #(#anchor)*
+        LanguageSpec {
+            extends: None,
+            without: vec![],
+            delimiters: #delimiters,
+            symmetric: [#((#symmetric.to_string(), Limit::#symmetric_limit)),*]
+                .into_iter()
+                .collect(),
+            generic: #generic,
+            line_comment: #line_comment,
+            block_comment: #block_comment,
+            string: #string,
+            char: #char,
+            block_string: #block_string,
+            regex: #regex,
+            quote_like: [#((#quote_like_prefix.to_string(), #quote_like_parts)),*]
+                .into_iter()
+                .collect(),
+            marker: #marker,
+            middle: #middle,
+            region: #region,
+            continuation: #continuation,
+            embedded: #embedded,
+            escape: [#((#escape_open.to_string(), Escape::#escape)),*]
+                .into_iter()
+                .collect(),
+            anchor,
+        }
+    }}
+}
+
+/// Errors on arms that can never match because an earlier arm matches the same text in the
+/// same states, i.e. a string and a delimiter both using `|`, or a closing delimiter that's the
+/// same as its opening delimiter. Arms of different sections are also ambiguous when the
+/// earlier arm only sometimes matches the same text in the same states, i.e. a regex and a
+/// symmetric delimiter both using `/`
+fn check_overlapping(def: &MatcherDef, match_arms: &[(&str, MatchArm)]) -> syn::Result<()> {
+    for (idx, (section, arm)) in match_arms.iter().enumerate() {
+        let earlier_arms = &match_arms[..idx];
+        if let Some((earlier_section, _)) = earlier_arms
+            .iter()
+            .find(|(_, earlier)| earlier.shadows(arm))
+        {
+            return Err(syn::Error::new(
+                def.name.span(),
+                format!(
+                    "`{}` in `{section}` is unreachable, it's already matched by `{earlier_section}`",
+                    arm.text()
+                ),
+            ));
+        }
+        let conflicts_with = earlier_arms.iter().find(|(earlier_section, earlier)| {
+            earlier_section != section && earlier.conflicts_with(arm)
+        });
+        if let Some((earlier_section, _)) = conflicts_with {
+            return Err(syn::Error::new(
+                def.name.span(),
+                format!(
+                    "`{}` in `{section}` is ambiguous, it's also matched by `{earlier_section}`",
+                    arm.text()
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Pushes the arms counting the nested pairs of bracketed delimiters inside of a quote-like
+/// operator in the state, i.e. `(` and `)` in `q(a(b))`
+fn push_nested_arms(
+    match_arms: &mut Vec<(&str, MatchArm)>,
+    open: &str,
+    open_delim: &str,
+    close_delim: &str,
+    max_lookahead: usize,
+) {
+    let nested_open_arm = MatchArm::builder(open_delim.to_string(), max_lookahead)
+        .input_state(quote! { State::InBlockString(#open, depth) })
+        .ignore_escaped()
+        .body(quote! { State::InBlockString(#open, depth + 1) });
+    match_arms.push(("quote_like", nested_open_arm));
+
+    let nested_close_arm = MatchArm::builder(close_delim.to_string(), max_lookahead)
+        .input_state(quote! { State::InBlockString(#open, depth @ 1..) })
+        .ignore_escaped()
+        .body(quote! { State::InBlockString(#open, depth - 1) });
+    match_arms.push(("quote_like", nested_close_arm));
+}
+
+/// Pushes the arm closing a string or block string, applying its escape rule:
+/// - `none`: closes even when preceded by a backslash
+/// - `backslash`: doesn't close when preceded by a backslash
+/// - `doubled`: the closing delimiter repeated twice is skipped, without closing
+fn push_close_arm<'a>(
+    match_arms: &mut Vec<(&'a str, MatchArm)>,
+    section: &'a str,
+    close_arm: MatchArm,
+    escape: Escape,
+    close: &str,
+    input_state: proc_macro2::TokenStream,
+    max_lookahead: usize,
+) {
+    match escape {
+        Escape::None => match_arms.push((section, close_arm)),
+        Escape::Backslash => match_arms.push((section, close_arm.ignore_escaped())),
+        Escape::Doubled => {
+            let doubled = close.repeat(2);
+            let escape_arm = MatchArm::builder(doubled.clone(), max_lookahead)
+                .input_state(input_state)
+                .body(quote! {
+                    // Skip tokens based on length of pattern
+                    for _ in 1..#doubled.len() {
+                        tokens.next();
+                    }
+                    state
+                });
+            match_arms.push(("escape", escape_arm));
+            match_arms.push((section, close_arm));
+        }
+    }
+}
+
+/// Errors on escapes that don't refer to a string or block string, after merging the base
+/// languages
+fn check_escapes(def: &MatcherDef) -> syn::Result<()> {
+    for (open, _) in &def.escapes {
+        let is_string = def.strings.contains(open)
+            || def
+                .block_strings
+                .iter()
+                .any(|(block_open, _)| block_open == open);
+        if !is_string {
+            return Err(syn::Error::new(
+                def.name.span(),
+                format!("escape `{open}` doesn't refer to a string or block string"),
+            ));
+        }
+    }
+    Ok(())
+}
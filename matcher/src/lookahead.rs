@@ -1,7 +1,7 @@
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote};
 
-use crate::config::MatcherDef;
+use crate::config::{Escape, MatcherDef, QUOTE_LIKE_DELIMITERS};
 
 /// Calculate the maximum number of characters we need to look ahead
 /// based on the longest string we need to match
@@ -13,6 +13,15 @@ pub fn calculate_max_lookahead(def: &MatcherDef) -> usize {
         max_len = max_len.max(close.len());
     }
 
+    for (delim, _) in &def.symmetrics {
+        max_len = max_len.max(delim.len());
+    }
+
+    for (open, close) in &def.generics {
+        max_len = max_len.max(open.len());
+        max_len = max_len.max(close.len());
+    }
+
     for comment in &def.line_comments {
         max_len = max_len.max(comment.len());
     }
@@ -24,17 +33,40 @@ pub fn calculate_max_lookahead(def: &MatcherDef) -> usize {
 
     for s in &def.strings {
         max_len = max_len.max(s.len());
+        // Doubled escapes match the closing delimiter twice
+        if def.escape(s) == Escape::Doubled {
+            max_len = max_len.max(s.len() * 2);
+        }
+    }
+
+    // Regex literals are lexed from the line after the opening delimiter
+    for (open, _) in &def.regexes {
+        max_len = max_len.max(open.len());
+    }
+
+    // The prefix of quote-like operators is lexed from the line before the delimiter
+    if !def.quote_likes.is_empty() {
+        for (open, _) in QUOTE_LIKE_DELIMITERS {
+            max_len = max_len.max(open.len());
+        }
     }
 
+    // Markers are lexed from the line after the first byte
+    for _ in def.markers.iter().chain(&def.middles) {
+        max_len = max_len.max(1);
+    }
+
+    // Char literals are lexed from the line, without lookahead
     for s in &def.chars {
-        // Always need to lookahead 2 extra bytes ahead for single-char strings
-        // So we can check for the second `'` in `'{'`
-        max_len = max_len.max(s.len() + 2);
+        max_len = max_len.max(s.len());
     }
 
     for (open, close) in &def.block_strings {
         max_len = max_len.max(open.len());
         max_len = max_len.max(close.len());
+        if def.escape(open) == Escape::Doubled {
+            max_len = max_len.max(close.len() * 2);
+        }
     }
 
     // Already have the first byte, so subtract 1
@@ -1,5 +1,7 @@
-use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote};
+
+use crate::config::Anchor;
 
 /// Generates the match header for the given lookahead
 ///
@@ -40,35 +42,127 @@ pub fn create_match_header(lookahead: usize) -> TokenStream2 {
 /// Generates: (#state, b'/', b'/', _, _) if #cond => { #body }
 pub struct MatchArm {
     pattern: String,
+    /// Text matched by the arm, which differs from the pattern when lexed from the line
+    text: String,
     lookahead: usize,
     adjacent: bool,
     _input_state: TokenStream2,
     _ignore_escaped: bool,
-    _if_condition: Option<TokenStream2>,
+    _lexed_condition: Option<TokenStream2>,
+    _if_conditions: Vec<TokenStream2>,
     _body: Option<TokenStream2>,
 }
 
+/// Alternative of an input state pattern, i.e. `State::InBlockString("q(", depth)`
+struct StatePattern {
+    /// Path of the variant, empty for the wildcard `_`
+    path: String,
+    /// Fields of the variant, `None` for wildcards and bindings
+    fields: Vec<Option<String>>,
+}
+
+impl StatePattern {
+    fn parse_alternatives(input_state: &TokenStream2) -> Vec<StatePattern> {
+        let empty = || StatePattern {
+            path: String::new(),
+            fields: vec![],
+        };
+        let mut alternatives = vec![empty()];
+        for token in input_state.clone() {
+            let alternative = alternatives.last_mut().unwrap();
+            match token {
+                TokenTree::Punct(punct) if punct.as_char() == '|' => alternatives.push(empty()),
+                TokenTree::Group(group) => alternative.fields = Self::parse_fields(group.stream()),
+                TokenTree::Ident(ident) if ident == "_" && alternative.path.is_empty() => {}
+                token => alternative.path.push_str(&token.to_string()),
+            }
+        }
+        alternatives
+    }
+
+    fn parse_fields(fields: TokenStream2) -> Vec<Option<String>> {
+        let mut split = vec![vec![]];
+        for token in fields {
+            match token {
+                TokenTree::Punct(punct) if punct.as_char() == ',' => split.push(vec![]),
+                token => split.last_mut().unwrap().push(token),
+            }
+        }
+        split
+            .into_iter()
+            .map(|field| match field.as_slice() {
+                [TokenTree::Ident(_)] => None,
+                _ => Some(TokenStream2::from_iter(field).to_string()),
+            })
+            .collect()
+    }
+
+    /// Whether every state matched by the other pattern is matched by this pattern
+    fn covers(&self, other: &StatePattern) -> bool {
+        self.path.is_empty()
+            || (self.path == other.path
+                && self.fields.len() == other.fields.len()
+                && (self.fields.iter().zip(&other.fields))
+                    .all(|(field, other)| field.is_none() || field == other))
+    }
+}
+
 impl MatchArm {
     pub fn builder(pattern: String, lookahead: usize) -> Self {
         let adjacent = pattern.len() > 1;
         Self {
+            text: pattern.clone(),
             pattern,
             lookahead,
             adjacent,
             _input_state: quote! { State::Normal },
             _ignore_escaped: false,
-            _if_condition: None,
+            _lexed_condition: None,
+            _if_conditions: vec![],
             _body: None,
         }
     }
 
-    pub fn input_state(mut self, input_state: TokenStream2) -> Self {
-        self._input_state = input_state;
-        self
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether this arm matches in every state the other arm matches in
+    fn covers_states(&self, other: &MatchArm) -> bool {
+        let states = StatePattern::parse_alternatives(&self._input_state);
+        StatePattern::parse_alternatives(&other._input_state)
+            .iter()
+            .all(|other| states.iter().any(|state| state.covers(other)))
+    }
+
+    /// Whether this arm matches in any of the states the other arm matches in
+    fn overlaps_states(&self, other: &MatchArm) -> bool {
+        let states = StatePattern::parse_alternatives(&self._input_state);
+        StatePattern::parse_alternatives(&other._input_state)
+            .iter()
+            .any(|other| {
+                states
+                    .iter()
+                    .any(|state| state.covers(other) || other.covers(state))
+            })
+    }
+
+    /// Whether this arm always matches when the other arm would, when placed before it
+    pub fn shadows(&self, other: &MatchArm) -> bool {
+        self.text == other.text
+            && self.covers_states(other)
+            && self._if_conditions.is_empty()
+            && (!self._ignore_escaped || other._ignore_escaped)
     }
 
-    pub fn non_adjacent(mut self) -> Self {
-        self.adjacent = false;
+    /// Whether this arm sometimes matches when the other arm would, when placed before it,
+    /// depending on the conditions of this arm
+    pub fn conflicts_with(&self, other: &MatchArm) -> bool {
+        self.text == other.text && self.overlaps_states(other) && !self._if_conditions.is_empty()
+    }
+
+    pub fn input_state(mut self, input_state: TokenStream2) -> Self {
+        self._input_state = input_state;
         self
     }
 
@@ -78,10 +172,29 @@ impl MatchArm {
     }
 
     pub fn if_condition(mut self, if_condition: TokenStream2) -> Self {
-        self._if_condition = Some(if_condition);
+        self._if_conditions.push(if_condition);
         self
     }
 
+    /// Matches the text lexed from the line when the condition holds, with the pattern only
+    /// matching part of it, i.e. the first byte of markers or the delimiter of quote-like
+    /// operators
+    pub fn lexed(mut self, text: &str, condition: TokenStream2) -> Self {
+        self.text = text.to_string();
+        self._lexed_condition = Some(condition);
+        self
+    }
+
+    /// Only matches when the anchor holds for the text, i.e. at the start of the line
+    pub fn anchor(self, anchor: Option<Anchor>) -> Self {
+        let Some(anchor) = anchor else {
+            return self;
+        };
+        let anchor = format_ident!("{}", anchor.name());
+        let len = self.text.len();
+        self.if_condition(quote! { Anchor::#anchor.matches(line, token.col, #len) })
+    }
+
     pub fn body(mut self, body: TokenStream2) -> Self {
         self._body = Some(body);
         self
@@ -122,14 +235,15 @@ impl MatchArm {
         let mut condition: TokenStream2 = condition.parse().unwrap();
 
         // Add if statement
-        if self.adjacent || self._if_condition.is_some() {
-            condition.extend(quote! { if });
-            if self.adjacent {
-                condition.extend(Self::adjacent_if_condition(&self.pattern));
-            }
-            if let Some(if_condition) = self._if_condition {
-                condition.extend(if_condition);
-            }
+        let mut if_conditions = self._if_conditions;
+        if let Some(lexed_condition) = self._lexed_condition {
+            if_conditions.insert(0, lexed_condition);
+        }
+        if self.adjacent {
+            if_conditions.insert(0, Self::adjacent_if_condition(&self.pattern));
+        }
+        if !if_conditions.is_empty() {
+            condition.extend(quote! { if #((#if_conditions))&&* });
         }
 
         // Combine condition and body
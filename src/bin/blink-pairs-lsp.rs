@@ -0,0 +1,588 @@
+//! Language server exposing the parser to editors other than Neovim over stdio. Publishes
+//! diagnostics for unbalanced pairs and answers folding range, selection range and document
+//! highlight (matching pair) requests.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use blink_pairs::buffer::ParsedBuffer;
+use blink_pairs::parser::{filetype, MatchWithLine, Token};
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentHighlightRequest, FoldingRangeRequest, Request as RequestTrait, SelectionRangeRequest,
+};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentHighlight, DocumentHighlightKind,
+    DocumentHighlightParams, FoldingRange, FoldingRangeKind, FoldingRangeParams,
+    FoldingRangeProviderCapability, OneOf, Position, PublishDiagnosticsParams, Range,
+    SelectionRange, SelectionRangeParams, SelectionRangeProviderCapability, ServerCapabilities,
+    TextDocumentContentChangeEvent, TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Sync + Send>>;
+
+struct Document {
+    filetype: Option<String>,
+    lines: Vec<String>,
+    parsed_buffer: Option<ParsedBuffer>,
+}
+
+impl Document {
+    fn new(uri: &Uri, language_id: &str, text: &str) -> Self {
+        // LSP language identifiers mostly match Neovim filetypes
+        let lines = split_lines(text);
+        let filetype = filetype::resolve(language_id)
+            .or_else(|| {
+                let first_line = lines.first().map(String::as_str).unwrap_or_default();
+                filetype::detect(uri.path().as_str(), first_line)
+            })
+            .map(str::to_string);
+
+        let mut document = Self {
+            filetype,
+            lines,
+            parsed_buffer: None,
+        };
+        document.parse();
+        document
+    }
+
+    fn parse(&mut self) {
+        self.parsed_buffer = self
+            .filetype
+            .as_ref()
+            .and_then(|filetype| ParsedBuffer::parse(filetype, &as_strs(&self.lines)));
+    }
+
+    /// Applies the changes, reparsing the changed lines of each change
+    fn apply_changes(&mut self, changes: Vec<TextDocumentContentChangeEvent>) {
+        for change in changes {
+            let Some(range) = change.range else {
+                // Full sync
+                self.lines = split_lines(&change.text);
+                self.parse();
+                continue;
+            };
+
+            // Order the positions, since a reversed range would otherwise panic when splicing
+            let mut positions = [
+                self.byte_position(range.start),
+                self.byte_position(range.end),
+            ];
+            positions.sort();
+            let [(start_row, start_col), (end_row, end_col)] = positions;
+
+            let text = format!(
+                "{}{}{}",
+                &self.lines[start_row][..start_col],
+                change.text,
+                &self.lines[end_row][end_col..]
+            );
+            let new_lines = split_lines(&text);
+            let new_end_row = start_row + new_lines.len();
+            self.lines.splice(start_row..=end_row, new_lines);
+
+            self.reparse(start_row, end_row + 1, new_end_row);
+        }
+    }
+
+    /// Reparses the lines between `start_line` and `new_end_line` (exclusive), which replaced
+    /// the lines until `old_end_line` (exclusive)
+    fn reparse(&mut self, start_line: usize, old_end_line: usize, new_end_line: usize) {
+        let (Some(filetype), Some(parsed_buffer)) =
+            (self.filetype.as_ref(), self.parsed_buffer.as_mut())
+        else {
+            return;
+        };
+
+        let old_state = parsed_buffer.line_state(old_end_line - 1);
+        parsed_buffer.reparse_range(
+            filetype,
+            &as_strs(&self.lines[start_line..new_end_line]),
+            Some(start_line),
+            Some(old_end_line),
+            Some(new_end_line),
+        );
+
+        // Later lines depend on the state at the end of the changed lines, i.e. when opening a
+        // block comment, so reparse them until the end of the buffer when it changed
+        if new_end_line < self.lines.len()
+            && parsed_buffer.line_state(new_end_line - 1) != old_state
+        {
+            parsed_buffer.reparse_range(
+                filetype,
+                &as_strs(&self.lines[new_end_line..]),
+                Some(new_end_line),
+                None,
+                None,
+            );
+        }
+    }
+
+    /// Converts an LSP position (UTF-16 code units) into a line and byte column, clamped to the
+    /// contents of the document
+    fn byte_position(&self, position: Position) -> (usize, usize) {
+        let row = (position.line as usize).min(self.lines.len() - 1);
+        let line = &self.lines[row];
+        if row < position.line as usize {
+            return (row, line.len());
+        }
+
+        let mut utf16_col = 0;
+        for (byte_col, char) in line.char_indices() {
+            if utf16_col >= position.character as usize {
+                return (row, byte_col);
+            }
+            utf16_col += char.len_utf16();
+        }
+        (row, line.len())
+    }
+
+    /// Converts a line and byte column into an LSP position (UTF-16 code units)
+    fn position(&self, row: usize, col: usize) -> Position {
+        let character = self
+            .lines
+            .get(row)
+            .map(|line| {
+                line.char_indices()
+                    .take_while(|(byte_col, _)| *byte_col < col)
+                    .map(|(_, char)| char.len_utf16())
+                    .sum::<usize>()
+            })
+            .unwrap_or(0);
+        Position::new(row as u32, character as u32)
+    }
+
+    fn match_range(&self, match_: &MatchWithLine) -> Range {
+        Range::new(
+            self.position(match_.line, match_.col),
+            self.position(match_.line, match_.col + match_.len()),
+        )
+    }
+
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        let Some(parsed_buffer) = &self.parsed_buffer else {
+            return vec![];
+        };
+
+        parsed_buffer
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| Diagnostic {
+                range: Range::new(
+                    self.position(diagnostic.line, diagnostic.col),
+                    self.position(diagnostic.line, diagnostic.col + diagnostic.len),
+                ),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("blink-pairs".to_string()),
+                message: diagnostic.message(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn folding_ranges(&self) -> Vec<FoldingRange> {
+        let Some(parsed_buffer) = &self.parsed_buffer else {
+            return vec![];
+        };
+
+        parsed_buffer
+            .pairs()
+            .into_iter()
+            .filter_map(|(open, close)| {
+                let (end_line, kind) = match open.token {
+                    Token::BlockComment(_, _) => (close.line, Some(FoldingRangeKind::Comment)),
+                    // Keep the closing delimiter visible
+                    Token::Delimiter(_, _) => (close.line.checked_sub(1)?, None),
+                    Token::Marker(_, _) | Token::Region(_, _) => {
+                        (close.line.checked_sub(1)?, Some(FoldingRangeKind::Region))
+                    }
+                    _ => (close.line, None),
+                };
+                if end_line <= open.line {
+                    return None;
+                }
+
+                Some(FoldingRange {
+                    start_line: open.line as u32,
+                    start_character: Some(
+                        self.position(open.line, open.col + open.len()).character,
+                    ),
+                    end_line: end_line as u32,
+                    end_character: None,
+                    kind,
+                    collapsed_text: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Builds the chain of ranges from the innermost pair surrounding the position, to the
+    /// outermost. Each pair contributes the range of its contents, and the range including the
+    /// opening and closing matches
+    fn selection_range(&self, position: Position) -> SelectionRange {
+        let empty = SelectionRange {
+            range: Range::new(position, position),
+            parent: None,
+        };
+        let Some(parsed_buffer) = &self.parsed_buffer else {
+            return empty;
+        };
+
+        let (row, col) = self.byte_position(position);
+        let mut ranges = vec![];
+        for (open, close) in parsed_buffer.pairs() {
+            let outer_start = (open.line, open.col);
+            let outer_end = (close.line, close.col + close.len());
+            if !(outer_start..=outer_end).contains(&(row, col)) {
+                continue;
+            }
+            ranges.push(Range::new(
+                self.position(open.line, open.col),
+                self.position(close.line, close.col + close.len()),
+            ));
+
+            let inner_start = (open.line, open.col + open.len());
+            let inner_end = (close.line, close.col);
+            if inner_start < inner_end && (inner_start..=inner_end).contains(&(row, col)) {
+                ranges.push(Range::new(
+                    self.position(open.line, open.col + open.len()),
+                    self.position(close.line, close.col),
+                ));
+            }
+        }
+
+        // Pairs are ordered by their opening position, so the outermost comes first
+        ranges.dedup();
+        ranges
+            .into_iter()
+            .fold(None, |parent, range| {
+                Some(SelectionRange {
+                    range,
+                    parent: parent.map(Box::new),
+                })
+            })
+            .unwrap_or(empty)
+    }
+
+    fn document_highlights(&self, position: Position) -> Vec<DocumentHighlight> {
+        let Some(parsed_buffer) = &self.parsed_buffer else {
+            return vec![];
+        };
+
+        // Check under the cursor, and just before it, like matchparen
+        let (row, col) = self.byte_position(position);
+        let pair = parsed_buffer.match_pair(row, col).or_else(|| {
+            col.checked_sub(1)
+                .and_then(|col| parsed_buffer.match_pair(row, col))
+        });
+
+        pair.map(|(open, close)| {
+            [open, close]
+                .iter()
+                .map(|match_| DocumentHighlight {
+                    range: self.match_range(match_),
+                    kind: Some(DocumentHighlightKind::TEXT),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+    }
+}
+
+fn as_strs(lines: &[String]) -> Vec<&str> {
+    lines.iter().map(String::as_str).collect()
+}
+
+fn split_lines(text: &str) -> Vec<String> {
+    text.split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+        .collect()
+}
+
+struct Server {
+    connection: Connection,
+    documents: HashMap<Uri, Document>,
+}
+
+impl Server {
+    fn publish_diagnostics(&self, uri: Uri, version: Option<i32>) -> Result<()> {
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map(Document::diagnostics)
+            .unwrap_or_default();
+        self.notify::<PublishDiagnostics>(PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version,
+        })
+    }
+
+    fn notify<N: NotificationTrait>(&self, params: N::Params) -> Result<()> {
+        let notification = Notification::new(N::METHOD.to_string(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+        Ok(())
+    }
+
+    fn respond(&self, id: RequestId, result: impl serde::Serialize) -> Result<()> {
+        let response = Response::new_ok(id, result);
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) -> Result<()> {
+        match request.method.as_str() {
+            FoldingRangeRequest::METHOD => {
+                let (id, params) =
+                    request.extract::<FoldingRangeParams>(FoldingRangeRequest::METHOD)?;
+                let ranges = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .map(Document::folding_ranges);
+                self.respond(id, ranges)
+            }
+            SelectionRangeRequest::METHOD => {
+                let (id, params) =
+                    request.extract::<SelectionRangeParams>(SelectionRangeRequest::METHOD)?;
+                let ranges = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .map(|document| {
+                        params
+                            .positions
+                            .iter()
+                            .map(|position| document.selection_range(*position))
+                            .collect::<Vec<_>>()
+                    });
+                self.respond(id, ranges)
+            }
+            DocumentHighlightRequest::METHOD => {
+                let (id, params) =
+                    request.extract::<DocumentHighlightParams>(DocumentHighlightRequest::METHOD)?;
+                let position = params.text_document_position_params;
+                let highlights = self
+                    .documents
+                    .get(&position.text_document.uri)
+                    .map(|document| document.document_highlights(position.position));
+                self.respond(id, highlights)
+            }
+            _ => {
+                let response = Response::new_err(
+                    request.id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("unhandled method: {}", request.method),
+                );
+                self.connection.sender.send(Message::Response(response))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = notification
+                    .extract::<<DidOpenTextDocument as NotificationTrait>::Params>(
+                        DidOpenTextDocument::METHOD,
+                    )?;
+                let document = params.text_document;
+                self.documents.insert(
+                    document.uri.clone(),
+                    Document::new(&document.uri, &document.language_id, &document.text),
+                );
+                self.publish_diagnostics(document.uri, Some(document.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = notification
+                    .extract::<<DidChangeTextDocument as NotificationTrait>::Params>(
+                        DidChangeTextDocument::METHOD,
+                    )?;
+                let uri = params.text_document.uri;
+                if let Some(document) = self.documents.get_mut(&uri) {
+                    document.apply_changes(params.content_changes);
+                }
+                self.publish_diagnostics(uri, Some(params.text_document.version))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = notification
+                    .extract::<<DidCloseTextDocument as NotificationTrait>::Params>(
+                        DidCloseTextDocument::METHOD,
+                    )?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                // Clear the diagnostics of the closed document
+                self.notify::<PublishDiagnostics>(PublishDiagnosticsParams {
+                    uri,
+                    diagnostics: vec![],
+                    version: None,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn run(mut self) -> Result<()> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.handle_request(request)?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+        document_highlight_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    if let Err(err) = connection.initialize(serde_json::to_value(capabilities)?) {
+        if err.channel_is_disconnected() {
+            io_threads.join()?;
+        }
+        return Err(err.into());
+    }
+
+    Server {
+        connection,
+        documents: HashMap::new(),
+    }
+    .run()?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rust_document(text: &str) -> Document {
+        Document::new(&"file:///test.rs".parse().unwrap(), "rust", text)
+    }
+
+    fn change(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: Some(Range::new(
+                Position::new(start.0, start.1),
+                Position::new(end.0, end.1),
+            )),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    /// Checks that the incremental parse matches a full parse of the document
+    fn assert_reparsed(document: &Document) {
+        let expected = rust_document(&document.lines.join("\n"));
+        let (parsed, expected) = (
+            document.parsed_buffer.as_ref().unwrap(),
+            expected.parsed_buffer.as_ref().unwrap(),
+        );
+        assert_eq!(parsed.line_count(), expected.line_count());
+        for line in 0..expected.line_count() {
+            assert_eq!(parsed.line_matches(line), expected.line_matches(line));
+            assert_eq!(parsed.line_state(line), expected.line_state(line));
+        }
+    }
+
+    #[test]
+    fn test_utf16_positions() {
+        // `é` is 2 bytes and 1 code unit, `😀` is 4 bytes and a surrogate pair of 2 code units
+        let document = rust_document("é😀(x)\n(");
+
+        assert_eq!(document.position(0, 6), Position::new(0, 3));
+        assert_eq!(document.byte_position(Position::new(0, 3)), (0, 6));
+        assert_eq!(document.position(0, 2), Position::new(0, 1));
+        assert_eq!(document.byte_position(Position::new(0, 1)), (0, 2));
+        // Inside of the surrogate pair, rounding to the end of the character
+        assert_eq!(document.byte_position(Position::new(0, 2)), (0, 6));
+        // Clamped to the end of the line and document
+        assert_eq!(document.byte_position(Position::new(0, 100)), (0, 9));
+        assert_eq!(document.byte_position(Position::new(5, 0)), (1, 1));
+
+        let highlights = document.document_highlights(Position::new(0, 3));
+        let ranges = highlights.iter().map(|h| h.range).collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            vec![
+                Range::new(Position::new(0, 3), Position::new(0, 4)),
+                Range::new(Position::new(0, 5), Position::new(0, 6)),
+            ]
+        );
+        assert_eq!(
+            document.diagnostics()[0].range,
+            Range::new(Position::new(1, 0), Position::new(1, 1))
+        );
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let mut document = rust_document("fn é() {\n    😀\n}");
+
+        // Insert after the surrogate pair
+        document.apply_changes(vec![change((1, 6), (1, 6), "(")]);
+        assert_eq!(document.lines[1], "    😀(");
+        assert_eq!(
+            document.diagnostics()[0].range,
+            Range::new(Position::new(1, 6), Position::new(1, 7))
+        );
+        assert_reparsed(&document);
+
+        // Replace across lines, with multiple changes applied in order
+        document.apply_changes(vec![
+            change((1, 6), (2, 1), "]\n} // é"),
+            change((1, 4), (1, 4), "["),
+        ]);
+        assert_eq!(document.lines, vec!["fn é() {", "    [😀]", "} // é"]);
+        assert!(document.diagnostics().is_empty());
+        assert_reparsed(&document);
+
+        // Opening a block comment changes the state of the following lines
+        document.apply_changes(vec![change((0, 7), (0, 7), "/*")]);
+        assert_eq!(document.lines[0], "fn é() /*{");
+        assert_reparsed(&document);
+        document.apply_changes(vec![change((0, 7), (0, 9), "")]);
+        assert!(document.diagnostics().is_empty());
+        assert_reparsed(&document);
+
+        // Reversed ranges are treated like the ordered range
+        document.apply_changes(vec![change((2, 1), (1, 6), "")]);
+        assert_eq!(document.lines, vec!["fn é() {", "    [😀 // é"]);
+        assert_reparsed(&document);
+        document.apply_changes(vec![change((1, 8), (1, 7), "]\n} ")]);
+        assert_eq!(document.lines, vec!["fn é() {", "    [😀]", "} // é"]);
+        assert!(document.diagnostics().is_empty());
+        assert_reparsed(&document);
+
+        // Full sync
+        document.apply_changes(vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "(\n".to_string(),
+        }]);
+        assert_eq!(document.lines, vec!["(", ""]);
+        assert_eq!(document.diagnostics().len(), 1);
+    }
+}
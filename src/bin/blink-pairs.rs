@@ -0,0 +1,228 @@
+//! Checks files for unbalanced delimiters, unterminated strings and unterminated block comments
+//! using the same parser as the Neovim plugin. Exits with 1 when any diagnostics were found.
+
+use std::io::Read;
+use std::process::ExitCode;
+
+use blink_pairs::buffer::ParsedBuffer;
+use blink_pairs::diagnostics::Diagnostic;
+use blink_pairs::parser::{filetype, Kind};
+use serde_json::{json, Value};
+
+const USAGE: &str = "\
+Usage: blink-pairs [OPTIONS] <FILE>...
+
+Checks files for unbalanced delimiters, unterminated strings and unterminated block comments.
+Pass `-` as the file to read from stdin, which requires `--filetype` or a shebang.
+
+Options:
+  -f, --filetype <FILETYPE>  Use the given filetype instead of detecting it from the extension or shebang
+      --format <FORMAT>      Output format, either `human` (default) or `json`
+      --dump                 Print the matches on each line instead of diagnostics
+  -h, --help                 Print this message";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Human,
+    Json,
+}
+
+struct Args {
+    filetype: Option<String>,
+    format: Format,
+    dump: bool,
+    files: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        filetype: None,
+        format: Format::Human,
+        dump: false,
+        files: vec![],
+    };
+
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            "-f" | "--filetype" => {
+                args.filetype = Some(argv.next().ok_or("missing value for --filetype")?);
+            }
+            "--format" => {
+                args.format = match argv.next().as_deref() {
+                    Some("human") => Format::Human,
+                    Some("json") => Format::Json,
+                    Some(format) => return Err(format!("unknown format `{format}`")),
+                    None => return Err("missing value for --format".to_string()),
+                };
+            }
+            "--dump" => args.dump = true,
+            "--" => args.files.extend(argv.by_ref()),
+            "-" => args.files.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+            _ => args.files.push(arg),
+        }
+    }
+
+    if args.files.is_empty() {
+        return Err("no files given".to_string());
+    }
+    Ok(args)
+}
+
+fn read_file(path: &str) -> std::io::Result<String> {
+    if path == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        std::fs::read_to_string(path)
+    }
+}
+
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Opening => "opening",
+        Kind::Closing => "closing",
+        Kind::NonPair => "non_pair",
+        Kind::Middle => "middle",
+    }
+}
+
+fn diagnostic_to_json(path: &str, diagnostic: &Diagnostic) -> Value {
+    json!({
+        "file": path,
+        // 1-indexed, to match the human readable output
+        "line": diagnostic.line + 1,
+        "col": diagnostic.col + 1,
+        "len": diagnostic.len,
+        "text": diagnostic.text(),
+        "message": diagnostic.message(),
+    })
+}
+
+fn dump_to_json(path: &str, parsed_buffer: &ParsedBuffer) -> Value {
+    let lines = (0..parsed_buffer.line_count())
+        .map(|line_number| {
+            let matches = parsed_buffer.line_matches(line_number).unwrap_or_default();
+            json!(matches
+                .iter()
+                .map(|match_| json!({
+                    "kind": kind_name(match_.kind),
+                    "text": match_.token.text(match_.kind),
+                    "col": match_.col,
+                    "stack_height": match_.stack_height,
+                    "type_stack_height": match_.type_stack_height,
+                }))
+                .collect::<Vec<_>>())
+        })
+        .collect::<Vec<_>>();
+
+    json!({ "file": path, "lines": lines })
+}
+
+fn print_dump(path: &str, parsed_buffer: &ParsedBuffer) {
+    println!("{path}");
+    for line_number in 0..parsed_buffer.line_count() {
+        let matches = parsed_buffer.line_matches(line_number).unwrap_or_default();
+        if matches.is_empty() {
+            continue;
+        }
+
+        let matches = matches
+            .iter()
+            .map(|match_| match match_.stack_height {
+                Some(stack_height) => {
+                    format!(
+                        "{}@{}[{}]",
+                        match_.token.text(match_.kind),
+                        match_.col,
+                        stack_height
+                    )
+                }
+                None => format!("{}@{}", match_.token.text(match_.kind), match_.col),
+            })
+            .collect::<Vec<_>>();
+        println!("{:>6}: {}", line_number + 1, matches.join(" "));
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut found_errors = false;
+    let mut failed = false;
+    let mut json_output = vec![];
+
+    for path in &args.files {
+        let text = match read_file(path) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("error: {path}: {err}");
+                failed = true;
+                continue;
+            }
+        };
+        let lines = text.lines().collect::<Vec<_>>();
+
+        let Some(filetype) = args
+            .filetype
+            .as_deref()
+            .or_else(|| filetype::detect(path, lines.first().copied().unwrap_or_default()))
+        else {
+            eprintln!("warning: {path}: unknown filetype, skipping");
+            continue;
+        };
+
+        let Some(parsed_buffer) = ParsedBuffer::parse(filetype, &lines) else {
+            eprintln!("error: {path}: unsupported filetype `{filetype}`");
+            failed = true;
+            continue;
+        };
+
+        if args.dump {
+            match args.format {
+                Format::Human => print_dump(path, &parsed_buffer),
+                Format::Json => json_output.push(dump_to_json(path, &parsed_buffer)),
+            }
+            continue;
+        }
+
+        let diagnostics = parsed_buffer.diagnostics();
+        found_errors |= !diagnostics.is_empty();
+        for diagnostic in &diagnostics {
+            match args.format {
+                Format::Human => println!(
+                    "{}:{}:{}: error: {}",
+                    path,
+                    diagnostic.line + 1,
+                    diagnostic.col + 1,
+                    diagnostic.message()
+                ),
+                Format::Json => json_output.push(diagnostic_to_json(path, diagnostic)),
+            }
+        }
+    }
+
+    if args.format == Format::Json {
+        println!("{}", Value::Array(json_output));
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else if found_errors {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -1,20 +1,71 @@
-use crate::parser::{parse_filetype, Kind, Match, MatchWithLine, State};
+use crate::diagnostics::{diagnose, Diagnostic};
+use crate::parser::{
+    fallback_matcher, parse, parse_filetype, DynamicMatcher, Kind, Match, MatchWithLine, State,
+    Token, CONFLICT_CLOSE,
+};
+
+/// Options for calculating the stack heights of the matches
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BufferOptions {
+    /// Calculate the stack heights of delimiters from only the first branch of marker pairs,
+    /// i.e. between `#if` and `#else`, so that unbalanced delimiters in the other branches don't
+    /// affect the delimiters after the pair
+    pub first_branch_only: bool,
+}
 
 pub struct ParsedBuffer {
     matches_by_line: Vec<Vec<Match>>,
     state_by_line: Vec<State>,
+    /// Matcher used when the filetype isn't supported, see [`ParsedBuffer::parse_or_fallback`]
+    fallback: Option<DynamicMatcher>,
+    options: BufferOptions,
 }
 
 impl ParsedBuffer {
     pub fn parse(filetype: &str, lines: &[&str]) -> Option<Self> {
         let (matches_by_line, state_by_line) = parse_filetype(filetype, lines, State::Normal)?;
 
-        Some(Self {
+        let mut buffer = Self {
             matches_by_line,
             state_by_line,
+            fallback: None,
+            options: BufferOptions::default(),
+        };
+        // The matchers don't calculate the heights of markers
+        buffer.recalculate_stack_heights();
+        Some(buffer)
+    }
+
+    /// Parses with the filetype's language when supported, otherwise with the generic
+    /// [`fallback_matcher`](crate::parser::fallback_matcher) using the comment syntax from the
+    /// `commentstring` (i.e. `# %s`). Subsequent calls to `reparse_range` keep using the fallback
+    pub fn parse_or_fallback(filetype: &str, commentstring: &str, lines: &[&str]) -> Self {
+        Self::parse(filetype, lines).unwrap_or_else(|| {
+            let fallback = fallback_matcher(commentstring);
+            let (matches_by_line, state_by_line) = parse(lines, State::Normal, fallback.clone());
+            let mut buffer = Self {
+                matches_by_line,
+                state_by_line,
+                fallback: Some(fallback),
+                options: BufferOptions::default(),
+            };
+            buffer.recalculate_stack_heights();
+            buffer
         })
     }
 
+    pub fn options(&self) -> BufferOptions {
+        self.options
+    }
+
+    /// Sets the options, recalculating the stack heights when they changed
+    pub fn set_options(&mut self, options: BufferOptions) {
+        if self.options != options {
+            self.options = options;
+            self.recalculate_stack_heights();
+        }
+    }
+
     pub fn reparse_range(
         &mut self,
         filetype: &str,
@@ -36,9 +87,12 @@ impl ParsedBuffer {
             State::Normal
         };
 
-        if let Some((matches_by_line, state_by_line)) =
-            parse_filetype(filetype, lines, initial_state)
-        {
+        let parsed = parse_filetype(filetype, lines, initial_state).or_else(|| {
+            self.fallback
+                .clone()
+                .map(|fallback| parse(lines, initial_state, fallback))
+        });
+        if let Some((matches_by_line, state_by_line)) = parsed {
             let new_end_line = new_end_line.unwrap_or(start_line + matches_by_line.len());
             let length = new_end_line - start_line;
             self.matches_by_line.splice(
@@ -56,10 +110,19 @@ impl ParsedBuffer {
         }
     }
 
+    pub fn line_count(&self) -> usize {
+        self.matches_by_line.len()
+    }
+
     pub fn line_matches(&self, line_number: usize) -> Option<Vec<Match>> {
         self.matches_by_line.get(line_number).cloned()
     }
 
+    /// State at the end of the given line
+    pub fn line_state(&self, line_number: usize) -> Option<State> {
+        self.state_by_line.get(line_number).copied()
+    }
+
     pub fn match_at(&self, line_number: usize, col: usize) -> Option<Match> {
         self.matches_by_line
             .get(line_number)?
@@ -68,6 +131,8 @@ impl ParsedBuffer {
             .cloned()
     }
 
+    /// Returns the opening and closing match of the pair at the position. For middles, i.e.
+    /// `#else`, returns the markers surrounding it, i.e. `#if` and `#endif`
     pub fn match_pair(
         &self,
         line_number: usize,
@@ -75,69 +140,353 @@ impl ParsedBuffer {
     ) -> Option<(MatchWithLine, MatchWithLine)> {
         let match_at_pos = self.match_at(line_number, col)?.with_line(line_number);
 
-        // Opening match
-        if match_at_pos.kind == Kind::Opening {
-            let closing_match = self.matches_by_line[line_number..]
-                .iter()
-                .enumerate()
-                .map(|(matches_line_number, matches)| (matches_line_number + line_number, matches))
-                .find_map(|(matches_line_number, matches)| {
-                    matches
-                        .iter()
-                        .find(|match_| {
-                            (line_number != matches_line_number || match_.col > col)
-                                && match_at_pos.token == match_.token
-                                && match_at_pos.stack_height == match_.stack_height
-                        })
-                        .map(|match_| match_.with_line(matches_line_number))
-                })?;
-
-            Some((match_at_pos, closing_match))
+        match match_at_pos.kind {
+            Kind::Opening => {
+                let closing_match = self.find_closing(&match_at_pos)?;
+                Some((match_at_pos, closing_match))
+            }
+            Kind::Closing => {
+                let opening_match = self.find_opening(&match_at_pos)?;
+                Some((opening_match, match_at_pos))
+            }
+            Kind::Middle => Some((
+                self.find_opening(&match_at_pos)?,
+                self.find_closing(&match_at_pos)?,
+            )),
+            Kind::NonPair => None,
         }
-        // Closing match
-        else if match_at_pos.kind == Kind::Closing {
-            let opening_match = self.matches_by_line[0..=line_number]
-                .iter()
-                .enumerate()
-                .rev()
-                .find_map(|(matches_line_number, matches)| {
-                    matches
-                        .iter()
-                        .rev()
-                        .find(|match_| {
-                            (line_number != matches_line_number || match_.col < col)
-                                && match_at_pos.token == match_.token
-                                && match_at_pos.stack_height == match_.stack_height
-                        })
-                        .map(|match_| match_.with_line(matches_line_number))
-                })?;
-
-            Some((opening_match, match_at_pos))
-        } else {
-            None
+    }
+
+    fn find_closing(&self, match_at_pos: &MatchWithLine) -> Option<MatchWithLine> {
+        let line_number = match_at_pos.line;
+        self.matches_by_line[line_number..]
+            .iter()
+            .enumerate()
+            .map(|(matches_line_number, matches)| (matches_line_number + line_number, matches))
+            .find_map(|(matches_line_number, matches)| {
+                matches
+                    .iter()
+                    .find(|match_| {
+                        (line_number != matches_line_number || match_.col > match_at_pos.col)
+                            && is_pair(match_at_pos, match_, Kind::Closing)
+                    })
+                    .map(|match_| match_.with_line(matches_line_number))
+            })
+    }
+
+    fn find_opening(&self, match_at_pos: &MatchWithLine) -> Option<MatchWithLine> {
+        let line_number = match_at_pos.line;
+        self.matches_by_line[0..=line_number]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(matches_line_number, matches)| {
+                matches
+                    .iter()
+                    .rev()
+                    .find(|match_| {
+                        (line_number != matches_line_number || match_.col < match_at_pos.col)
+                            && is_pair(match_at_pos, match_, Kind::Opening)
+                    })
+                    .map(|match_| match_.with_line(matches_line_number))
+            })
+    }
+
+    /// Returns every opening and closing pair in the buffer, ordered by the position of the
+    /// opening match. Unbalanced delimiters are skipped
+    pub fn pairs(&self) -> Vec<(MatchWithLine, MatchWithLine)> {
+        let mut pairs = vec![];
+        let mut delimiter_stack: Vec<MatchWithLine> = vec![];
+        let mut marker_stack: Vec<MatchWithLine> = vec![];
+        let mut region_stack: Vec<MatchWithLine> = vec![];
+        let mut embedded_stack: Vec<MatchWithLine> = vec![];
+        let mut open: Option<MatchWithLine> = None;
+
+        for (line_number, matches) in self.matches_by_line.iter().enumerate() {
+            for match_ in matches {
+                match (&match_.token, match_.kind) {
+                    // Delimiters, regions and embedded delimiters are paired on their own stacks
+                    (Token::Delimiter(..) | Token::Region(..) | Token::Embedded(..), _) => {
+                        let stack = match match_.token {
+                            Token::Delimiter(..) => &mut delimiter_stack,
+                            Token::Region(..) => &mut region_stack,
+                            _ => &mut embedded_stack,
+                        };
+                        match match_.kind {
+                            Kind::Opening => stack.push(match_.with_line(line_number)),
+                            Kind::Closing => {
+                                if let Some(idx) = stack
+                                    .iter()
+                                    .rposition(|opening| opening.token == match_.token)
+                                {
+                                    let opening = stack.drain(idx..).next().unwrap();
+                                    pairs.push((opening, match_.with_line(line_number)));
+                                }
+                            }
+                            Kind::NonPair | Kind::Middle => {}
+                        }
+                    }
+                    (Token::Marker(_, _), Kind::Opening) => {
+                        marker_stack.push(match_.with_line(line_number))
+                    }
+                    (Token::Marker(_, close), Kind::Closing) => {
+                        if let Some(idx) = marker_stack
+                            .iter()
+                            .rposition(|opening| opening.token.closing() == Some(close))
+                        {
+                            let opening = marker_stack.drain(idx..).next().unwrap();
+                            pairs.push((opening, match_.with_line(line_number)));
+                        }
+                    }
+                    // Strings and comments can't be nested
+                    (_, Kind::Opening) => open = Some(match_.with_line(line_number)),
+                    (_, Kind::Closing) => {
+                        if let Some(opening) = open.take_if(|opening| opening.token == match_.token)
+                        {
+                            pairs.push((opening, match_.with_line(line_number)));
+                        }
+                    }
+                    (_, Kind::NonPair | Kind::Middle) => {}
+                }
+            }
         }
+
+        pairs.sort_by_key(|(opening, _)| (opening.line, opening.col));
+        pairs
+    }
+
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        diagnose(&self.matches_by_line, &self.state_by_line)
     }
 
     fn recalculate_stack_heights(&mut self) {
+        // Same as the matchers, the stack holds the closing delimiter of each open pair
         let mut stack = vec![];
+        // Markers, regions and embedded delimiters are on their own stacks
+        let mut marker_stack: Vec<OpenMarker> = vec![];
+        let mut region_stack = vec![];
+        let mut embedded_stack = vec![];
+        let first_branch_only = self.options.first_branch_only;
 
         for matches in self.matches_by_line.iter_mut() {
             for match_ in matches {
-                // Opening delimiter
-                if match_.kind == Kind::Opening {
-                    match_.stack_height = Some(stack.len());
-                    stack.push(&match_.token);
-                }
-                // Closing delimiter
-                else {
-                    if let Some(closing) = stack.last() {
-                        if *closing == &match_.token {
+                match (&match_.token, match_.kind) {
+                    // Opening delimiter
+                    (Token::Delimiter(_, close), Kind::Opening) => {
+                        match_.stack_height = Some(stack.len());
+                        match_.type_stack_height = Some(type_height(&stack, close));
+                        stack.push(*close);
+                    }
+                    // Closing delimiter
+                    (Token::Delimiter(_, close), _) => {
+                        if stack.last() == Some(close) {
                             stack.pop();
                         }
+                        match_.stack_height = Some(stack.len());
+                        match_.type_stack_height = Some(type_height(&stack, close));
+                    }
+                    (Token::Marker(_, close), Kind::Opening) => {
+                        match_.stack_height = Some(marker_stack.len());
+                        marker_stack.push(OpenMarker {
+                            close,
+                            start: stack.clone(),
+                            first_branch: None,
+                        });
+                    }
+                    (Token::Marker(_, close), Kind::Middle) => {
+                        let height = marker_stack.len();
+                        match marker_stack.last_mut() {
+                            Some(open) if open.close == *close => {
+                                match_.stack_height = Some(height - 1);
+                                // Each branch starts from the delimiters before the pair. The
+                                // sides of git conflicts always do, since only one of them is kept
+                                if first_branch_only || *close == CONFLICT_CLOSE {
+                                    open.first_branch.get_or_insert_with(|| stack.clone());
+                                    stack = open.start.clone();
+                                }
+                            }
+                            _ => match_.stack_height = Some(height),
+                        }
+                    }
+                    (Token::Marker(_, close), _) => {
+                        let open = marker_stack.pop_if(|open| open.close == *close);
+                        // Continue from the delimiters at the end of the first branch
+                        if let Some(first_branch) = open
+                            .and_then(|open| open.first_branch)
+                            .filter(|_| first_branch_only || *close == CONFLICT_CLOSE)
+                        {
+                            stack = first_branch;
+                        }
+                        match_.stack_height = Some(marker_stack.len());
                     }
-                    match_.stack_height = Some(stack.len());
+                    (Token::Region(..), Kind::Opening) => {
+                        match_.stack_height = Some(region_stack.len());
+                        region_stack.push(match_.token.clone());
+                    }
+                    (Token::Region(..), _) => {
+                        if region_stack.last() == Some(&match_.token) {
+                            region_stack.pop();
+                        }
+                        match_.stack_height = Some(region_stack.len());
+                    }
+                    (Token::Embedded(_, close), Kind::Opening) => {
+                        match_.stack_height = Some(embedded_stack.len());
+                        embedded_stack.push(*close);
+                    }
+                    (Token::Embedded(_, close), _) => {
+                        if embedded_stack.last() == Some(close) {
+                            embedded_stack.pop();
+                        }
+                        match_.stack_height = Some(embedded_stack.len());
+                    }
+                    _ => {}
                 }
             }
         }
     }
 }
+
+/// Number of open delimiters of the same type on the stack, for rainbow levels independent of
+/// the other types of delimiters
+fn type_height(stack: &[&str], close: &str) -> usize {
+    stack.iter().filter(|open| **open == close).count()
+}
+
+/// Marker pair being calculated by [`ParsedBuffer::recalculate_stack_heights`]
+struct OpenMarker<'a> {
+    close: &'a str,
+    /// Delimiter stack at the opening marker
+    start: Vec<&'a str>,
+    /// Delimiter stack at the end of the first branch, once a middle is found
+    first_branch: Option<Vec<&'a str>>,
+}
+
+/// Whether the match is the other side of the pair of the given match. Opening and middle
+/// markers vary for the same closing marker, i.e. `#if` and `#ifdef`, so markers are paired by
+/// their closing marker and kind
+fn is_pair(match_at_pos: &MatchWithLine, match_: &Match, kind: Kind) -> bool {
+    match_at_pos.stack_height == match_.stack_height
+        && match (&match_at_pos.token, &match_.token) {
+            (Token::Marker(_, close), Token::Marker(_, other_close)) => {
+                close == other_close && match_.kind == kind
+            }
+            (token, other_token) => token == other_token,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markers() {
+        let lines = [
+            "#ifdef A",
+            "int f() {",
+            "  #if B",
+            "  #endif",
+            "#else",
+            "int f(int x) {",
+            "#endif",
+            "}",
+        ];
+        let mut buffer = ParsedBuffer::parse("c", &lines).unwrap();
+        let height =
+            |buffer: &ParsedBuffer, line: usize| buffer.line_matches(line).unwrap()[0].stack_height;
+
+        let (open, close) = buffer.match_pair(4, 0).unwrap();
+        assert_eq!((open.line, open.token.opening()), (0, "#ifdef"));
+        assert_eq!((close.line, close.token.opening()), (6, "#endif"));
+        let (open, close) = buffer.match_pair(2, 3).unwrap();
+        assert_eq!((open.line, close.line), (2, 3));
+        assert_eq!(buffer.pairs().len(), 5);
+
+        // Both branches open a brace
+        assert_eq!(height(&buffer, 5), Some(1));
+        assert_eq!(height(&buffer, 7), Some(1));
+
+        buffer.set_options(BufferOptions {
+            first_branch_only: true,
+        });
+        assert_eq!(height(&buffer, 5), Some(0));
+        assert_eq!(height(&buffer, 7), Some(0));
+    }
+
+    #[test]
+    fn test_type_stack_heights() {
+        let buffer = ParsedBuffer::parse("rust", &["{ f([(a)]) }"]).unwrap();
+        let heights = buffer
+            .line_matches(0)
+            .unwrap()
+            .iter()
+            .map(|m| (m.stack_height.unwrap(), m.type_stack_height.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            heights,
+            vec![
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (3, 1),
+                (3, 1),
+                (2, 0),
+                (1, 0),
+                (0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_regions() {
+        let lines = ["-- #region", "-- {{{", "-- }}}", "-- #endregion"];
+        let buffer = ParsedBuffer::parse("lua", &lines).unwrap();
+
+        let (open, close) = buffer.match_pair(0, 3).unwrap();
+        assert_eq!((open.line, close.line), (0, 3));
+        let (open, close) = buffer.match_pair(2, 3).unwrap();
+        assert_eq!((open.line, close.line), (1, 2));
+        assert_eq!(buffer.pairs().len(), 2);
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let lines = [
+            "<<<<<<< HEAD",
+            "fn f(a: u8) {",
+            "||||||| base",
+            "fn f() {",
+            "=======",
+            "fn f(a: u8, b: u8) {",
+            ">>>>>>> feature",
+            "}",
+        ];
+        let buffer = ParsedBuffer::parse("rust", &lines).unwrap();
+
+        for line in [2, 4] {
+            let (open, close) = buffer.match_pair(line, 0).unwrap();
+            assert_eq!((open.line, close.line), (0, 6));
+        }
+        // Each side opens a brace, closed after the conflict
+        let (open, close) = buffer.match_pair(7, 0).unwrap();
+        assert_eq!((open.line, open.stack_height), (5, Some(0)));
+        assert_eq!(close.stack_height, Some(0));
+        assert_eq!(buffer.pairs().len(), 5);
+    }
+
+    #[test]
+    fn test_reparse_nested_quote_like() {
+        let lines = ["q{a {", "b", "} c}", "(d)"];
+        let mut buffer = ParsedBuffer::parse("perl", &lines).unwrap();
+
+        // Reparsing from inside of the nested pair continues from the depth of the previous line
+        let lines = ["q{a {", "b(", "} c}", "(d)"];
+        assert!(buffer.reparse_range("perl", &lines[1..], Some(1), None, None));
+        let expected = ParsedBuffer::parse("perl", &lines).unwrap();
+        for line in 0..lines.len() {
+            assert_eq!(buffer.line_matches(line), expected.line_matches(line));
+            assert_eq!(buffer.line_state(line), expected.line_state(line));
+        }
+        let (open, close) = buffer.match_pair(0, 0).unwrap();
+        assert_eq!((open.line, close.line, close.col), (0, 2, 3));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::parser::{Kind, Match, State, Token, CONFLICT_CLOSE};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    /// Closing delimiter without any opening delimiter before it
+    UnmatchedDelimiter,
+    /// Closing delimiter that doesn't match the innermost opening delimiter
+    MismatchedDelimiter { expected: &'static str },
+    /// Opening delimiter that is never closed
+    UnclosedDelimiter,
+    /// String or block string without a closing delimiter
+    UnterminatedString,
+    /// Block comment without a closing delimiter
+    UnterminatedBlockComment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub token: Token,
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+impl Diagnostic {
+    fn new(kind: DiagnosticKind, match_: &Match, line: usize) -> Self {
+        Self {
+            kind,
+            token: match_.token.clone(),
+            line,
+            col: match_.col,
+            len: match_.len(),
+        }
+    }
+
+    /// The text of the token the diagnostic points at
+    pub fn text(&self) -> &'static str {
+        match self.kind {
+            DiagnosticKind::UnmatchedDelimiter | DiagnosticKind::MismatchedDelimiter { .. } => {
+                self.token.closing().unwrap_or_else(|| self.token.opening())
+            }
+            _ => self.token.opening(),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match &self.kind {
+            DiagnosticKind::UnmatchedDelimiter => {
+                format!("unmatched closing delimiter `{}`", self.text())
+            }
+            DiagnosticKind::MismatchedDelimiter { expected } => format!(
+                "mismatched closing delimiter `{}`, expected `{}`",
+                self.text(),
+                expected
+            ),
+            DiagnosticKind::UnclosedDelimiter => {
+                format!("unclosed delimiter `{}`", self.text())
+            }
+            DiagnosticKind::UnterminatedString => {
+                format!("unterminated string `{}`", self.text())
+            }
+            DiagnosticKind::UnterminatedBlockComment => {
+                format!("unterminated block comment `{}`", self.text())
+            }
+        }
+    }
+}
+
+/// Walks the matches of a parsed buffer and reports unbalanced delimiters,
+/// unterminated strings and unterminated block comments, sorted by position
+pub fn diagnose(matches_by_line: &[Vec<Match>], state_by_line: &[State]) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut stack: Vec<(usize, &Match)> = vec![];
+    let mut open_string: Option<(usize, &Match)> = None;
+    // Each side of a git conflict starts from the delimiters before the conflict, and the first
+    // side continues after it
+    let mut conflicts = vec![];
+
+    for (line_number, matches) in matches_by_line.iter().enumerate() {
+        for match_ in matches {
+            match (&match_.token, match_.kind) {
+                (Token::Delimiter(_, _), Kind::Opening) => stack.push((line_number, match_)),
+                (Token::Delimiter(_, _), Kind::Closing) => {
+                    // Closes the innermost delimiter
+                    if let Some((_, opening)) = stack.last() {
+                        if opening.token == match_.token {
+                            stack.pop();
+                            continue;
+                        }
+                    }
+
+                    // Closes a delimiter further down the stack, so everything above it is unclosed
+                    if let Some(idx) = stack.iter().rposition(|(_, m)| m.token == match_.token) {
+                        for (line, opening) in stack.drain(idx..).skip(1) {
+                            diagnostics.push(Diagnostic::new(
+                                DiagnosticKind::UnclosedDelimiter,
+                                opening,
+                                line,
+                            ));
+                        }
+                        continue;
+                    }
+
+                    let kind = match stack.last() {
+                        Some((_, opening)) => DiagnosticKind::MismatchedDelimiter {
+                            expected: opening.token.closing().unwrap_or_default(),
+                        },
+                        None => DiagnosticKind::UnmatchedDelimiter,
+                    };
+                    diagnostics.push(Diagnostic::new(kind, match_, line_number));
+                }
+
+                (Token::Marker(_, CONFLICT_CLOSE), Kind::Opening) => {
+                    conflicts.push((stack.clone(), None))
+                }
+                (Token::Marker(_, CONFLICT_CLOSE), Kind::Middle) => {
+                    if let Some((start, first_side)) = conflicts.last_mut() {
+                        first_side.get_or_insert_with(|| stack.clone());
+                        stack = start.clone();
+                    }
+                }
+                (Token::Marker(_, CONFLICT_CLOSE), _) => {
+                    if let Some(first_side) = conflicts.pop().and_then(|(_, first_side)| first_side)
+                    {
+                        stack = first_side;
+                    }
+                }
+
+                (Token::String(_), Kind::Opening) => open_string = Some((line_number, match_)),
+                (Token::String(_), Kind::Closing) => open_string = None,
+
+                _ => {}
+            }
+        }
+
+        // Strings end at the end of the line, unless continued with a backslash
+        let continued = line_number + 1 < matches_by_line.len()
+            && matches!(state_by_line.get(line_number), Some(State::InString(_)));
+        if !continued {
+            if let Some((line, opening)) = open_string.take() {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::UnterminatedString,
+                    opening,
+                    line,
+                ));
+            }
+        }
+    }
+
+    for (line, opening) in stack {
+        diagnostics.push(Diagnostic::new(
+            DiagnosticKind::UnclosedDelimiter,
+            opening,
+            line,
+        ));
+    }
+
+    // Block strings and block comments are unterminated when the buffer ends inside of them
+    let unterminated = match state_by_line.last() {
+        Some(State::InBlockString(open, _)) => Some((DiagnosticKind::UnterminatedString, *open)),
+        Some(State::InBlockComment(open)) => {
+            Some((DiagnosticKind::UnterminatedBlockComment, *open))
+        }
+        _ => None,
+    };
+    if let Some((kind, open)) = unterminated {
+        let opening =
+            matches_by_line
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(line_number, matches)| {
+                    matches
+                        .iter()
+                        .rev()
+                        .find(|m| {
+                            m.kind == Kind::Opening
+                                && matches!(
+                                    m.token,
+                                    Token::BlockString(..) | Token::BlockComment(..)
+                                )
+                                && m.token.opening() == open
+                        })
+                        .map(|m| (line_number, m))
+                });
+        if let Some((line, opening)) = opening {
+            diagnostics.push(Diagnostic::new(kind, opening, line));
+        }
+    }
+
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.col));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::ParsedBuffer;
+
+    fn diagnose_c(text: &str) -> Vec<(DiagnosticKind, usize, usize)> {
+        ParsedBuffer::parse("c", &text.split('\n').collect::<Vec<_>>())
+            .unwrap()
+            .diagnostics()
+            .into_iter()
+            .map(|d| (d.kind, d.line, d.col))
+            .collect()
+    }
+
+    #[test]
+    fn test_diagnose() {
+        assert_eq!(diagnose_c("{\n  (a[0]);\n}"), vec![]);
+        assert_eq!(
+            diagnose_c("{\n  (a[0];\n}"),
+            vec![(DiagnosticKind::UnclosedDelimiter, 1, 2)]
+        );
+        assert_eq!(
+            diagnose_c("(]"),
+            vec![
+                (DiagnosticKind::UnclosedDelimiter, 0, 0),
+                (DiagnosticKind::MismatchedDelimiter { expected: ")" }, 0, 1),
+            ]
+        );
+        assert_eq!(
+            diagnose_c("}"),
+            vec![(DiagnosticKind::UnmatchedDelimiter, 0, 0)]
+        );
+        assert_eq!(
+            diagnose_c("x = \"abc;\n"),
+            vec![(DiagnosticKind::UnterminatedString, 0, 4)]
+        );
+        assert_eq!(diagnose_c("x = \"a \\\nb\";"), vec![]);
+        assert_eq!(
+            diagnose_c("x = \"a \\\nb;"),
+            vec![(DiagnosticKind::UnterminatedString, 0, 4)]
+        );
+        assert_eq!(
+            diagnose_c("/* {\n}"),
+            vec![(DiagnosticKind::UnterminatedBlockComment, 0, 0)]
+        );
+        assert_eq!(
+            diagnose_c("<<<<<<< HEAD\nf(a) {\n=======\nf(a, b) {\n>>>>>>> b\n}"),
+            vec![]
+        );
+    }
+}
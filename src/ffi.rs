@@ -0,0 +1,474 @@
+//! C ABI for embedding the parser in hosts other than Neovim, such as Vim9 (via a small C
+//! plugin) or Emacs dynamic modules. The header is checked in at `include/blink_pairs.h`, and
+//! regenerated with `cbindgen --output include/blink_pairs.h` after changing this module.
+//!
+//! Conventions:
+//! - Strings passed in are UTF-8 and described by a pointer and a length, except for the
+//!   filetype which is NUL terminated
+//! - Strings passed out (token text) have a static lifetime and are *not* NUL terminated
+//! - Functions filling a caller provided array return the total number of items, so the caller
+//!   may call again with a larger array when the return value exceeds the capacity
+//! - Lines and columns are 0-indexed, and columns are in bytes
+//! - Functions which parse catch panics and report them as a failure, since unwinding into the
+//!   host is undefined behavior
+
+use std::ffi::{c_char, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::buffer::ParsedBuffer;
+use crate::parser::{Kind, Match, MatchWithLine, State, TokenType};
+
+/// Parsed buffer, created with `blink_pairs_buffer_new` and freed with `blink_pairs_buffer_free`
+pub struct BlinkPairsBuffer(ParsedBuffer);
+
+/// Borrowed UTF-8 string, not NUL terminated
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct BlinkPairsStr {
+    pub ptr: *const c_char,
+    pub len: usize,
+}
+
+impl BlinkPairsStr {
+    const EMPTY: Self = Self {
+        ptr: std::ptr::null(),
+        len: 0,
+    };
+
+    unsafe fn as_str<'a>(&self) -> Option<&'a str> {
+        if self.len == 0 {
+            return Some("");
+        }
+        if self.ptr.is_null() {
+            return None;
+        }
+        std::str::from_utf8(std::slice::from_raw_parts(self.ptr as *const u8, self.len)).ok()
+    }
+}
+
+impl From<&'static str> for BlinkPairsStr {
+    fn from(str: &'static str) -> Self {
+        Self {
+            ptr: str.as_ptr() as *const c_char,
+            len: str.len(),
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum BlinkPairsKind {
+    Opening = 0,
+    Closing = 1,
+    NonPair = 2,
+    Middle = 3,
+}
+
+impl From<Kind> for BlinkPairsKind {
+    fn from(kind: Kind) -> Self {
+        match kind {
+            Kind::Opening => Self::Opening,
+            Kind::Closing => Self::Closing,
+            Kind::NonPair => Self::NonPair,
+            Kind::Middle => Self::Middle,
+        }
+    }
+}
+
+/// Same values as the `token_type` accepted by `get_line_matches` in Lua
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum BlinkPairsTokenType {
+    Delimiter = 0,
+    String = 1,
+    BlockString = 2,
+    LineComment = 3,
+    BlockComment = 4,
+    Marker = 5,
+    Region = 6,
+    Embedded = 7,
+}
+
+impl From<TokenType> for BlinkPairsTokenType {
+    fn from(token_type: TokenType) -> Self {
+        match token_type {
+            TokenType::Delimiter => Self::Delimiter,
+            TokenType::String => Self::String,
+            TokenType::BlockString => Self::BlockString,
+            TokenType::LineComment => Self::LineComment,
+            TokenType::BlockComment => Self::BlockComment,
+            TokenType::Marker => Self::Marker,
+            TokenType::Region => Self::Region,
+            TokenType::Embedded => Self::Embedded,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct BlinkPairsMatch {
+    pub kind: BlinkPairsKind,
+    pub token_type: BlinkPairsTokenType,
+    pub opening: BlinkPairsStr,
+    /// Empty for tokens without a closing text, such as strings and line comments
+    pub closing: BlinkPairsStr,
+    pub line: usize,
+    pub col: usize,
+    /// Length of the match in bytes
+    pub len: usize,
+    /// Only set for delimiters
+    pub has_stack_height: bool,
+    pub stack_height: usize,
+    /// Height among the delimiters of the same type, only set for delimiters
+    pub has_type_stack_height: bool,
+    pub type_stack_height: usize,
+}
+
+impl BlinkPairsMatch {
+    fn new(match_: &MatchWithLine) -> Self {
+        Self {
+            kind: match_.kind.into(),
+            token_type: TokenType::from(&match_.token).into(),
+            opening: match_.token.opening().into(),
+            closing: match_
+                .token
+                .closing()
+                .map(Into::into)
+                .unwrap_or(BlinkPairsStr::EMPTY),
+            line: match_.line,
+            col: match_.col,
+            len: match_.len(),
+            has_stack_height: match_.stack_height.is_some(),
+            stack_height: match_.stack_height.unwrap_or(0),
+            has_type_stack_height: match_.type_stack_height.is_some(),
+            type_stack_height: match_.type_stack_height.unwrap_or(0),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct BlinkPairsPair {
+    pub opening: BlinkPairsMatch,
+    pub closing: BlinkPairsMatch,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub enum BlinkPairsStateKind {
+    Normal = 0,
+    InString = 1,
+    InBlockString = 2,
+    InLineComment = 3,
+    InBlockComment = 4,
+}
+
+/// Parser state at the end of a line
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct BlinkPairsState {
+    pub kind: BlinkPairsStateKind,
+    /// Opening text of the string or comment, empty for `Normal` and `InLineComment`
+    pub token: BlinkPairsStr,
+}
+
+impl From<State> for BlinkPairsState {
+    fn from(state: State) -> Self {
+        let (kind, token) = match state {
+            State::Normal => (BlinkPairsStateKind::Normal, BlinkPairsStr::EMPTY),
+            State::InString(open) => (BlinkPairsStateKind::InString, open.into()),
+            State::InBlockString(open, _) => (BlinkPairsStateKind::InBlockString, open.into()),
+            State::InLineComment => (BlinkPairsStateKind::InLineComment, BlinkPairsStr::EMPTY),
+            State::InBlockComment(open) => (BlinkPairsStateKind::InBlockComment, open.into()),
+        };
+        Self { kind, token }
+    }
+}
+
+unsafe fn to_lines<'a>(lines: *const BlinkPairsStr, line_count: usize) -> Option<Vec<&'a str>> {
+    if line_count == 0 {
+        return Some(vec![]);
+    }
+    if lines.is_null() {
+        return None;
+    }
+    std::slice::from_raw_parts(lines, line_count)
+        .iter()
+        .map(|line| line.as_str())
+        .collect()
+}
+
+unsafe fn to_filetype<'a>(filetype: *const c_char) -> Option<&'a str> {
+    if filetype.is_null() {
+        return None;
+    }
+    CStr::from_ptr(filetype).to_str().ok()
+}
+
+/// Writes as many items as fit into `out` and returns the total number of items
+unsafe fn write_items<T>(
+    items: impl ExactSizeIterator<Item = T>,
+    out: *mut T,
+    capacity: usize,
+) -> usize {
+    let total = items.len();
+    if !out.is_null() {
+        for (idx, item) in items.take(capacity).enumerate() {
+            out.add(idx).write(item);
+        }
+    }
+    total
+}
+
+fn optional_line(line: i64) -> Option<usize> {
+    usize::try_from(line).ok()
+}
+
+/// Whether the edited range lies within the buffer, and the new lines are all provided
+fn valid_range(
+    line_count: usize,
+    new_line_count: usize,
+    start_line: Option<usize>,
+    old_end_line: Option<usize>,
+    new_end_line: Option<usize>,
+) -> bool {
+    let start_line = start_line.unwrap_or(0);
+    start_line <= line_count
+        && old_end_line.is_none_or(|old_end_line| (start_line..=line_count).contains(&old_end_line))
+        && new_end_line.is_none_or(|new_end_line| {
+            (start_line..=start_line + new_line_count).contains(&new_end_line)
+        })
+}
+
+/// Parses the lines with the language of the filetype. Returns NULL when the filetype is not
+/// supported, or when the filetype or lines are not valid UTF-8.
+///
+/// # Safety
+///
+/// `filetype` must be a valid NUL terminated string and `lines` must point to `line_count`
+/// valid strings. The strings only need to live for the duration of the call.
+#[no_mangle]
+pub unsafe extern "C" fn blink_pairs_buffer_new(
+    filetype: *const c_char,
+    lines: *const BlinkPairsStr,
+    line_count: usize,
+) -> *mut BlinkPairsBuffer {
+    let (Some(filetype), Some(lines)) = (to_filetype(filetype), to_lines(lines, line_count)) else {
+        return std::ptr::null_mut();
+    };
+
+    match catch_unwind(|| ParsedBuffer::parse(filetype, &lines)) {
+        Ok(Some(parsed_buffer)) => Box::into_raw(Box::new(BlinkPairsBuffer(parsed_buffer))),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Frees a buffer created with `blink_pairs_buffer_new`. Passing NULL is a no-op.
+///
+/// # Safety
+///
+/// `buffer` must be NULL or a pointer returned by `blink_pairs_buffer_new` which hasn't been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn blink_pairs_buffer_free(buffer: *mut BlinkPairsBuffer) {
+    if !buffer.is_null() {
+        drop(Box::from_raw(buffer));
+    }
+}
+
+/// Reparses the lines between `start_line` and `old_end_line` (exclusive), which have been
+/// replaced by `lines`, ending at `new_end_line` (exclusive). Matches the arguments of Neovim's
+/// `on_lines` callback. Pass `-1` for all three to reparse the whole buffer with `lines`.
+/// Returns false when the filetype is not supported, or when the range is outside of the buffer
+/// or covers more lines than given.
+///
+/// # Safety
+///
+/// `buffer` must be a valid buffer, `filetype` a valid NUL terminated string and `lines` must
+/// point to `line_count` valid strings.
+#[no_mangle]
+pub unsafe extern "C" fn blink_pairs_buffer_reparse(
+    buffer: *mut BlinkPairsBuffer,
+    filetype: *const c_char,
+    lines: *const BlinkPairsStr,
+    line_count: usize,
+    start_line: i64,
+    old_end_line: i64,
+    new_end_line: i64,
+) -> bool {
+    let Some(buffer) = buffer.as_mut() else {
+        return false;
+    };
+    let (Some(filetype), Some(lines)) = (to_filetype(filetype), to_lines(lines, line_count)) else {
+        return false;
+    };
+
+    let (start_line, old_end_line, new_end_line) = (
+        optional_line(start_line),
+        optional_line(old_end_line),
+        optional_line(new_end_line),
+    );
+    if !valid_range(
+        buffer.0.line_count(),
+        lines.len(),
+        start_line,
+        old_end_line,
+        new_end_line,
+    ) {
+        return false;
+    }
+
+    // The buffer may be left half updated by a panic, but it remains safe to query
+    catch_unwind(AssertUnwindSafe(|| {
+        buffer
+            .0
+            .reparse_range(filetype, &lines, start_line, old_end_line, new_end_line)
+    }))
+    .unwrap_or(false)
+}
+
+/// # Safety
+///
+/// `buffer` must be a valid buffer.
+#[no_mangle]
+pub unsafe extern "C" fn blink_pairs_buffer_line_count(buffer: *const BlinkPairsBuffer) -> usize {
+    buffer
+        .as_ref()
+        .map(|buffer| buffer.0.line_count())
+        .unwrap_or(0)
+}
+
+/// Writes up to `capacity` matches on the line into `out`, and returns the total number of
+/// matches on the line. `out` may be NULL to only query the count.
+///
+/// # Safety
+///
+/// `buffer` must be a valid buffer and `out` must be NULL or point to `capacity` writable
+/// matches.
+#[no_mangle]
+pub unsafe extern "C" fn blink_pairs_buffer_line_matches(
+    buffer: *const BlinkPairsBuffer,
+    line: usize,
+    out: *mut BlinkPairsMatch,
+    capacity: usize,
+) -> usize {
+    let Some(matches) = buffer
+        .as_ref()
+        .and_then(|buffer| buffer.0.line_matches(line))
+    else {
+        return 0;
+    };
+
+    write_items(
+        matches
+            .iter()
+            .map(|match_: &Match| BlinkPairsMatch::new(&match_.with_line(line))),
+        out,
+        capacity,
+    )
+}
+
+/// Writes the state at the end of the line into `out`. Returns false when the line is out of
+/// range.
+///
+/// # Safety
+///
+/// `buffer` must be a valid buffer and `out` must point to a writable state.
+#[no_mangle]
+pub unsafe extern "C" fn blink_pairs_buffer_line_state(
+    buffer: *const BlinkPairsBuffer,
+    line: usize,
+    out: *mut BlinkPairsState,
+) -> bool {
+    match buffer.as_ref().and_then(|buffer| buffer.0.line_state(line)) {
+        Some(state) if !out.is_null() => {
+            out.write(state.into());
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Writes the match at the position into `out`. Returns false when there's no match.
+///
+/// # Safety
+///
+/// `buffer` must be a valid buffer and `out` must point to a writable match.
+#[no_mangle]
+pub unsafe extern "C" fn blink_pairs_buffer_match_at(
+    buffer: *const BlinkPairsBuffer,
+    line: usize,
+    col: usize,
+    out: *mut BlinkPairsMatch,
+) -> bool {
+    match buffer
+        .as_ref()
+        .and_then(|buffer| buffer.0.match_at(line, col))
+    {
+        Some(match_) if !out.is_null() => {
+            out.write(BlinkPairsMatch::new(&match_.with_line(line)));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Writes the pair of the match at the position into `out`. Returns false when there's no
+/// match, or it has no counterpart.
+///
+/// # Safety
+///
+/// `buffer` must be a valid buffer and `out` must point to a writable pair.
+#[no_mangle]
+pub unsafe extern "C" fn blink_pairs_buffer_match_pair(
+    buffer: *const BlinkPairsBuffer,
+    line: usize,
+    col: usize,
+    out: *mut BlinkPairsPair,
+) -> bool {
+    match buffer
+        .as_ref()
+        .and_then(|buffer| buffer.0.match_pair(line, col))
+    {
+        Some((opening, closing)) if !out.is_null() => {
+            out.write(BlinkPairsPair {
+                opening: BlinkPairsMatch::new(&opening),
+                closing: BlinkPairsMatch::new(&closing),
+            });
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Writes up to `capacity` pairs of the buffer into `out`, ordered by the position of the
+/// opening match, and returns the total number of pairs. `out` may be NULL to only query the
+/// count.
+///
+/// # Safety
+///
+/// `buffer` must be a valid buffer and `out` must be NULL or point to `capacity` writable
+/// pairs.
+#[no_mangle]
+pub unsafe extern "C" fn blink_pairs_buffer_pairs(
+    buffer: *const BlinkPairsBuffer,
+    out: *mut BlinkPairsPair,
+    capacity: usize,
+) -> usize {
+    let Some(buffer) = buffer.as_ref() else {
+        return 0;
+    };
+
+    write_items(
+        buffer
+            .0
+            .pairs()
+            .iter()
+            .map(|(opening, closing)| BlinkPairsPair {
+                opening: BlinkPairsMatch::new(opening),
+                closing: BlinkPairsMatch::new(closing),
+            }),
+        out,
+        capacity,
+    )
+}
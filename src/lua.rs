@@ -0,0 +1,351 @@
+use mlua::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex, MutexGuard};
+
+use crate::buffer::{BufferOptions, ParsedBuffer};
+use crate::parser::{self, Anchor, Escape, LanguageSpec, Limit, Match, MatchWithLine, TokenType};
+
+static PARSED_BUFFERS: LazyLock<Mutex<HashMap<usize, ParsedBuffer>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn get_parsed_buffers<'a>() -> MutexGuard<'a, HashMap<usize, ParsedBuffer>> {
+    match PARSED_BUFFERS.lock() {
+        Ok(lock) => lock,
+        Err(_) => {
+            // Reset the mutex
+            PARSED_BUFFERS.clear_poison();
+            let mut parsed_buffers = PARSED_BUFFERS.lock().unwrap();
+            *parsed_buffers = HashMap::new();
+            parsed_buffers
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn parse_buffer(
+    _lua: &Lua,
+    (bufnr, filetype, lines, start_line, old_end_line, new_end_line, commentstring, options): (
+        usize,
+        String,
+        Vec<String>,
+        Option<usize>,
+        Option<usize>,
+        Option<usize>,
+        Option<String>,
+        Option<BufferOptions>,
+    ),
+) -> LuaResult<bool> {
+    let options = options.unwrap_or_default();
+    let lines_ref = lines.iter().map(|str| str.as_ref()).collect::<Vec<_>>();
+
+    let mut parsed_buffers = get_parsed_buffers();
+
+    // Incremental parse
+    if let Some(parsed_buffer) = parsed_buffers.get_mut(&bufnr) {
+        parsed_buffer.set_options(options);
+        Ok(parsed_buffer.reparse_range(
+            &filetype,
+            &lines_ref,
+            start_line,
+            old_end_line,
+            new_end_line,
+        ))
+    }
+    // Full parse, falling back to a generic matcher when given a commentstring
+    else if let Some(commentstring) = commentstring {
+        let mut parsed_buffer =
+            ParsedBuffer::parse_or_fallback(&filetype, &commentstring, &lines_ref);
+        parsed_buffer.set_options(options);
+        parsed_buffers.insert(bufnr, parsed_buffer);
+        Ok(true)
+    } else if let Some(mut parsed_buffer) = ParsedBuffer::parse(&filetype, &lines_ref) {
+        parsed_buffer.set_options(options);
+        parsed_buffers.insert(bufnr, parsed_buffer);
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+fn get_line_matches(
+    _lua: &Lua,
+    (bufnr, line_number, token_type): (usize, usize, Option<u8>),
+) -> LuaResult<Vec<Match>> {
+    let parsed_buffers = get_parsed_buffers();
+    let token_type = token_type
+        // TODO: don't ignore the error
+        .and_then(|token_type| token_type.try_into().ok())
+        .unwrap_or(TokenType::Delimiter);
+
+    if let Some(parsed_buffer) = parsed_buffers.get(&bufnr) {
+        if let Some(line_matches) = parsed_buffer.line_matches(line_number) {
+            return Ok(line_matches
+                .iter()
+                .filter(|m| token_type.matches(&m.token))
+                .cloned()
+                .collect());
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+fn get_match_at(_lua: &Lua, (bufnr, row, col): (usize, usize, usize)) -> LuaResult<Option<Match>> {
+    Ok(get_parsed_buffers()
+        .get(&bufnr)
+        .and_then(|parsed_buffer| parsed_buffer.match_at(row, col)))
+}
+
+fn get_match_pair(
+    _lua: &Lua,
+    (bufnr, row, col): (usize, usize, usize),
+) -> LuaResult<Option<Vec<MatchWithLine>>> {
+    Ok(get_parsed_buffers()
+        .get(&bufnr)
+        .and_then(|parsed_buffer| parsed_buffer.match_pair(row, col))
+        .map(|(open, close)| vec![open, close]))
+}
+
+fn register_language(_lua: &Lua, (filetype, spec): (String, LanguageSpec)) -> LuaResult<()> {
+    parser::register_language(&filetype, &spec).map_err(LuaError::RuntimeError)
+}
+
+fn register_language_file(_lua: &Lua, (filetype, path): (String, String)) -> LuaResult<()> {
+    let spec = LanguageSpec::from_file(Path::new(&path)).map_err(LuaError::RuntimeError)?;
+    parser::register_language(&filetype, &spec).map_err(LuaError::RuntimeError)
+}
+
+fn get_language_spec(_lua: &Lua, filetype: String) -> LuaResult<Option<LanguageSpec>> {
+    Ok(parser::language_spec(&filetype))
+}
+
+fn supported_filetypes(_lua: &Lua, _: ()) -> LuaResult<Vec<String>> {
+    Ok(parser::supported_filetypes())
+}
+
+// NOTE: skip_memory_check greatly improves performance
+// https://github.com/mlua-rs/mlua/issues/318
+#[mlua::lua_module(skip_memory_check)]
+fn blink_pairs(lua: &Lua) -> LuaResult<LuaTable> {
+    let exports = lua.create_table()?;
+    exports.set("parse_buffer", lua.create_function(parse_buffer)?)?;
+    exports.set("get_line_matches", lua.create_function(get_line_matches)?)?;
+    exports.set("get_match_at", lua.create_function(get_match_at)?)?;
+    exports.set("get_match_pair", lua.create_function(get_match_pair)?)?;
+    exports.set("register_language", lua.create_function(register_language)?)?;
+    exports.set(
+        "register_language_file",
+        lua.create_function(register_language_file)?,
+    )?;
+    exports.set("get_language_spec", lua.create_function(get_language_spec)?)?;
+    exports.set(
+        "supported_filetypes",
+        lua.create_function(supported_filetypes)?,
+    )?;
+    Ok(exports)
+}
+
+impl FromLua for BufferOptions {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let table = LuaTable::from_lua(value, lua)?;
+        Ok(BufferOptions {
+            first_branch_only: table
+                .get::<Option<bool>>("first_branch_only")?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl IntoLua for Match {
+    fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+        let table = lua.create_table()?;
+
+        table.set(0, self.token.opening())?;
+        if let Some(closing) = self.token.closing() {
+            table.set(1, closing)?;
+        }
+        table.set("col", self.col)?;
+        table.set("len", self.len())?;
+        table.set("stack_height", self.stack_height)?;
+        table.set("type_stack_height", self.type_stack_height)?;
+
+        (&table).into_lua(lua)
+    }
+}
+
+impl IntoLua for MatchWithLine {
+    fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+        let table = lua.create_table()?;
+
+        table.set(1, self.token.opening())?;
+        if let Some(closing) = self.token.closing() {
+            table.set(2, closing)?;
+        }
+        table.set("line", self.line)?;
+        table.set("col", self.col)?;
+        table.set("len", self.len())?;
+        table.set("stack_height", self.stack_height)?;
+        table.set("type_stack_height", self.type_stack_height)?;
+
+        (&table).into_lua(lua)
+    }
+}
+
+/// Same shape as accepted by `register_language`, with the sections of the base language
+/// merged in
+impl IntoLua for LanguageSpec {
+    fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+        let pairs = |pairs: Vec<(String, String)>| -> Vec<[String; 2]> {
+            pairs
+                .into_iter()
+                .map(|(open, close)| [open, close])
+                .collect()
+        };
+
+        let table = lua.create_table()?;
+        table.set("delimiters", pairs(self.delimiters))?;
+        let symmetric = self.symmetric.into_iter().map(|(delim, limit)| {
+            let limit = match limit {
+                Limit::None => "none",
+                Limit::Line => "line",
+            };
+            (delim, limit)
+        });
+        table.set("symmetric", lua.create_table_from(symmetric)?)?;
+        table.set("generic", pairs(self.generic))?;
+        table.set("line_comment", self.line_comment)?;
+        table.set("block_comment", pairs(self.block_comment))?;
+        table.set("string", self.string)?;
+        table.set("char", self.char)?;
+        table.set("block_string", pairs(self.block_string))?;
+        table.set("regex", pairs(self.regex))?;
+        table.set("quote_like", self.quote_like)?;
+        table.set("marker", pairs(self.marker))?;
+        table.set("middle", pairs(self.middle))?;
+        table.set("region", pairs(self.region))?;
+        table.set("continuation", self.continuation)?;
+        table.set("embedded", self.embedded)?;
+        let escape = self.escape.into_iter().map(|(open, escape)| {
+            let escape = match escape {
+                Escape::None => "none",
+                Escape::Backslash => "backslash",
+                Escape::Doubled => "doubled",
+            };
+            (open, escape)
+        });
+        table.set("escape", lua.create_table_from(escape)?)?;
+        let anchor = lua.create_table()?;
+        for (section, anchors) in self.anchor {
+            let anchors = anchors.into_iter().map(|(pattern, anchor)| {
+                let anchor = match anchor {
+                    Anchor::LineStart => "line_start",
+                    Anchor::Indent => "indent",
+                    Anchor::Whitespace => "whitespace",
+                    Anchor::Word => "word",
+                    Anchor::WordStart => "word_start",
+                    Anchor::OutsideNumber => "outside_number",
+                };
+                (pattern, anchor)
+            });
+            anchor.set(section, lua.create_table_from(anchors)?)?;
+        }
+        table.set("anchor", anchor)?;
+
+        (&table).into_lua(lua)
+    }
+}
+
+impl FromLua for LanguageSpec {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let table = LuaTable::from_lua(value, lua)?;
+
+        let get_list = |key: &str| -> LuaResult<Vec<String>> {
+            Ok(table.get::<Option<Vec<String>>>(key)?.unwrap_or_default())
+        };
+        // Pairs are defined as a list of `{ open, close }` since the order matters
+        let get_pairs = |key: &str| -> LuaResult<Vec<(String, String)>> {
+            table
+                .get::<Option<Vec<Vec<String>>>>(key)?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|pair| match <[String; 2]>::try_from(pair) {
+                    Ok([open, close]) => Ok((open, close)),
+                    Err(_) => Err(LuaError::RuntimeError(format!(
+                        "{key}: expected a list of {{ open, close }} pairs"
+                    ))),
+                })
+                .collect()
+        };
+
+        Ok(LanguageSpec {
+            extends: table.get("extends")?,
+            without: get_list("without")?,
+            delimiters: get_pairs("delimiters")?,
+            symmetric: table
+                .get::<Option<HashMap<String, String>>>("symmetric")?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(delim, limit)| match limit.as_str() {
+                    "none" => Ok((delim, Limit::None)),
+                    "line" => Ok((delim, Limit::Line)),
+                    _ => Err(LuaError::RuntimeError(format!(
+                        "symmetric: expected 'none' or 'line' for \"{delim}\""
+                    ))),
+                })
+                .collect::<LuaResult<_>>()?,
+            generic: get_pairs("generic")?,
+            line_comment: get_list("line_comment")?,
+            block_comment: get_pairs("block_comment")?,
+            string: get_list("string")?,
+            char: get_list("char")?,
+            block_string: get_pairs("block_string")?,
+            regex: get_pairs("regex")?,
+            quote_like: table
+                .get::<Option<HashMap<String, usize>>>("quote_like")?
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+            marker: get_pairs("marker")?,
+            middle: get_pairs("middle")?,
+            region: get_pairs("region")?,
+            continuation: get_list("continuation")?,
+            embedded: get_list("embedded")?,
+            escape: table
+                .get::<Option<HashMap<String, String>>>("escape")?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(open, escape)| match escape.as_str() {
+                    "none" => Ok((open, Escape::None)),
+                    "backslash" => Ok((open, Escape::Backslash)),
+                    "doubled" => Ok((open, Escape::Doubled)),
+                    _ => Err(LuaError::RuntimeError(format!(
+                        "escape: expected 'none', 'backslash' or 'doubled' for \"{open}\""
+                    ))),
+                })
+                .collect::<LuaResult<_>>()?,
+            anchor: table
+                .get::<Option<HashMap<String, HashMap<String, String>>>>("anchor")?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(section, anchors)| {
+                    let anchors = anchors
+                        .into_iter()
+                        .map(|(pattern, anchor)| match anchor.as_str() {
+                            "line_start" => Ok((pattern, Anchor::LineStart)),
+                            "indent" => Ok((pattern, Anchor::Indent)),
+                            "whitespace" => Ok((pattern, Anchor::Whitespace)),
+                            "word" => Ok((pattern, Anchor::Word)),
+                            "word_start" => Ok((pattern, Anchor::WordStart)),
+                            "outside_number" => Ok((pattern, Anchor::OutsideNumber)),
+                            _ => Err(LuaError::RuntimeError(format!(
+                                "anchor: expected 'line_start', 'indent', 'whitespace', 'word', 'word_start' or 'outside_number' for \"{pattern}\""
+                            ))),
+                        })
+                        .collect::<LuaResult<_>>()?;
+                    Ok((section, anchors))
+                })
+                .collect::<LuaResult<_>>()?,
+        })
+    }
+}
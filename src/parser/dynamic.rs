@@ -0,0 +1,1541 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+
+use itertools::MultiPeek;
+
+use super::{
+    char_literal_len, closes_generic, marker_at, opens_generic, quote_like_second_part,
+    quote_like_start, regex_literal_len, region_in_comment, skip_to_col, unwind_symmetric, Anchor,
+    CharPos, Escape, Kind, Limit, Match, Matcher, MatcherMetadata, State, Token,
+    QUOTE_LIKE_DELIMITERS,
+};
+
+/// Runtime description of a language, with the same sections as `define_matcher!`
+///
+/// With the `spec` feature, it may be loaded from TOML or JSON, where missing sections are
+/// empty and pairs are written as two element arrays:
+///
+/// ```toml
+/// delimiters = [["(", ")"], ["{", "}"]]
+/// generic = [["<", ">"]]
+/// line_comment = ["//"]
+/// block_comment = [["/*", "*/"]]
+/// string = ["\""]
+/// regex = [["/", "/"]]
+/// continuation = ["string", "line_comment"]
+/// embedded = ["block_string"]
+/// marker = [["#if", "#endif"]]
+/// middle = [["#else", "#endif"]]
+/// region = [["#region", "#endregion"]]
+///
+/// [quote_like]
+/// q = 1
+/// s = 2
+///
+/// [symmetric]
+/// "|" = "line"
+///
+/// [anchor.line_comment]
+/// "#" = "whitespace"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(
+    feature = "spec",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct LanguageSpec {
+    /// Filetype of the base language, whose sections are extended by the sections of this spec
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "Option::is_none"))]
+    pub extends: Option<String>,
+    /// Sections of the base language to remove, i.e. `["char"]`
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub without: Vec<String>,
+    pub delimiters: Vec<(String, String)>,
+    /// Delimiters opening and closing with the same pattern, with how far they may extend, i.e.
+    /// `|` limited to the line for closure parameters
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "BTreeMap::is_empty"))]
+    pub symmetric: BTreeMap<String, Limit>,
+    /// Angle brackets of generic arguments, i.e. `<` and `>` in `Vec<T>`, only opening after an
+    /// identifier or `::` and dropped when not closed on the same line
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub generic: Vec<(String, String)>,
+    pub line_comment: Vec<String>,
+    pub block_comment: Vec<(String, String)>,
+    pub string: Vec<String>,
+    pub char: Vec<String>,
+    pub block_string: Vec<(String, String)>,
+    /// Regex literals on a single line, reported as block strings
+    pub regex: Vec<(String, String)>,
+    /// Prefixes of quote-like operators with the delimiter chosen at the use site (see
+    /// [`QUOTE_LIKE_DELIMITERS`](super::QUOTE_LIKE_DELIMITERS)), and their number of parts, i.e.
+    /// `1` for `q{..}` and `2` for `s/../../`. Reported as block strings
+    pub quote_like: BTreeMap<String, usize>,
+    /// Line-based pairs, lexed from the line, i.e. `#if` and `#endif`
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub marker: Vec<(String, String)>,
+    /// Separators between the branches of a marker pair, with the closing marker of the pair,
+    /// i.e. `#else` and `#endif`
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub middle: Vec<(String, String)>,
+    /// Region markers inside of line comments, i.e. `#region` and `#endregion` in
+    /// `// #region`
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub region: Vec<(String, String)>,
+    /// Sections continuing onto the next line when the line ends with a backslash, either
+    /// `string` or `line_comment`
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub continuation: Vec<String>,
+    /// Sections in which delimiters are still matched, as embedded delimiters paired on their
+    /// own stack, any of `string`, `block_string`, `line_comment` or `block_comment`
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub embedded: Vec<String>,
+    /// Escape rules of strings and block strings, by opening delimiter, defaulting to
+    /// [`Escape::Backslash`]
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "BTreeMap::is_empty"))]
+    pub escape: BTreeMap<String, Escape>,
+    /// Anchors of patterns, by section and pattern, i.e. `#` in `line_comment` only starting a
+    /// comment when preceded by whitespace
+    #[cfg_attr(feature = "spec", serde(skip_serializing_if = "BTreeMap::is_empty"))]
+    pub anchor: BTreeMap<String, BTreeMap<String, Anchor>>,
+}
+
+impl LanguageSpec {
+    /// Merges the sections of the base language into the spec, like `extends` in
+    /// `define_matcher!`. The base language may be built-in or registered at runtime
+    pub fn resolve(&self) -> Result<LanguageSpec, String> {
+        let Some(extends) = &self.extends else {
+            if !self.without.is_empty() {
+                return Err("without: requires a base language with extends".to_string());
+            }
+            return Ok(self.clone());
+        };
+        let mut base = crate::parser::language_spec(extends)
+            .ok_or_else(|| format!("extends: unknown language \"{extends}\""))?;
+
+        for section in &self.without {
+            base.anchor.remove(section);
+            match section.as_str() {
+                "delimiters" => base.delimiters.clear(),
+                "symmetric" => base.symmetric.clear(),
+                "generic" => base.generic.clear(),
+                "line_comment" => base.line_comment.clear(),
+                "block_comment" => base.block_comment.clear(),
+                "string" => base.string.clear(),
+                "char" => base.char.clear(),
+                "block_string" => base.block_string.clear(),
+                "regex" => base.regex.clear(),
+                "quote_like" => base.quote_like.clear(),
+                "marker" => base.marker.clear(),
+                "middle" => base.middle.clear(),
+                "region" => base.region.clear(),
+                "continuation" => base.continuation.clear(),
+                "embedded" => base.embedded.clear(),
+                "escape" => base.escape.clear(),
+                _ => return Err(format!("without: unknown section \"{section}\"")),
+            }
+        }
+
+        base.delimiters.extend(self.delimiters.iter().cloned());
+        base.symmetric.extend(self.symmetric.clone());
+        base.generic.extend(self.generic.iter().cloned());
+        base.line_comment.extend(self.line_comment.iter().cloned());
+        base.block_comment
+            .extend(self.block_comment.iter().cloned());
+        base.string.extend(self.string.iter().cloned());
+        base.char.extend(self.char.iter().cloned());
+        base.block_string.extend(self.block_string.iter().cloned());
+        base.regex.extend(self.regex.iter().cloned());
+        base.quote_like.extend(self.quote_like.clone());
+        base.marker.extend(self.marker.iter().cloned());
+        base.middle.extend(self.middle.iter().cloned());
+        base.region.extend(self.region.iter().cloned());
+        base.continuation.extend(self.continuation.iter().cloned());
+        base.embedded.extend(self.embedded.iter().cloned());
+        base.escape.extend(self.escape.clone());
+        for (section, anchors) in &self.anchor {
+            base.anchor
+                .entry(section.clone())
+                .or_default()
+                .extend(anchors.clone());
+        }
+        Ok(base)
+    }
+}
+
+#[cfg(feature = "spec")]
+impl LanguageSpec {
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|err| err.to_string())
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|err| err.to_string())
+    }
+
+    /// Loads a `.toml` or `.json` file, based on the extension
+    pub fn from_file(path: &std::path::Path) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let spec = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&text),
+            Some("json") => Self::from_json(&text),
+            _ => Err("expected a .toml or .json file".to_string()),
+        };
+        spec.map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|err| err.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Action {
+    /// Markers, lexed from the line after their first byte
+    OpenMarker(&'static str, &'static str),
+    MiddleMarker(&'static str, &'static str),
+    CloseMarker(&'static str),
+    OpenBlockComment(&'static str, &'static str),
+    CloseBlockComment(&'static str, &'static str),
+    OpenBlockString(&'static str, &'static str),
+    CloseBlockString(&'static str, &'static str),
+    LineComment(&'static str),
+    OpenString(&'static str),
+    CloseString(&'static str),
+    /// Regex literal, lexed from the line
+    Regex(&'static str, &'static str),
+    /// Quote-like operator, matched on the delimiter with the prefix lexed from the line
+    OpenQuote(&'static str, &'static str),
+    /// Pair nested inside of a quote-like operator, counted in the state
+    NestQuote,
+    /// End of the first part of a quote-like operator with two parts, i.e. `s/a/`
+    QuoteMiddle(&'static str),
+    /// Closes the nested pair, or the operator when no pairs are nested
+    CloseQuote(&'static str, &'static str),
+    /// End of the first bracketed part of a quote-like operator with two parts, waiting for the
+    /// second part in the given state, i.e. `s{a}`
+    CloseQuoteFirstPart(&'static str, &'static str, &'static str),
+    /// Start of the second bracketed part
+    OpenQuoteSecondPart(&'static str),
+    /// Char literal, lexed from the line
+    Char(&'static str),
+    OpenDelimiter(&'static str, &'static str),
+    CloseDelimiter(&'static str, &'static str),
+    /// Opens, or closes when open at the top of the stack
+    Symmetric(&'static str),
+    /// Opens when preceded by an identifier, see [`opens_generic`]
+    OpenGeneric(&'static str, &'static str),
+    /// Closes when open at the top of the stack, otherwise it's an operator
+    CloseGeneric(&'static str, &'static str),
+    /// Delimiter inside of a string or comment listed in `embedded`, keeping the state
+    Embedded(&'static str, &'static str, Kind),
+    /// Closing delimiter repeated twice inside of a string, skipped without closing
+    DoubledEscape,
+}
+
+/// Equivalent of a match arm generated by `define_matcher!`
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: &'static [u8],
+    input_state: State,
+    ignore_escaped: bool,
+    anchor: Option<Anchor>,
+    action: Action,
+}
+
+impl Action {
+    fn section(&self) -> &'static str {
+        match self {
+            Action::OpenMarker(..) | Action::CloseMarker(_) => "marker",
+            Action::MiddleMarker(..) => "middle",
+            Action::OpenBlockComment(..) | Action::CloseBlockComment(..) => "block_comment",
+            Action::OpenBlockString(..) | Action::CloseBlockString(..) => "block_string",
+            Action::LineComment(_) => "line_comment",
+            Action::OpenString(_) | Action::CloseString(_) => "string",
+            Action::Regex(..) => "regex",
+            Action::OpenQuote(..)
+            | Action::NestQuote
+            | Action::QuoteMiddle(_)
+            | Action::CloseQuote(..)
+            | Action::CloseQuoteFirstPart(..)
+            | Action::OpenQuoteSecondPart(_) => "quote_like",
+            Action::Char(_) => "char",
+            // Anchors are shared with the delimiters
+            Action::OpenDelimiter(..) | Action::CloseDelimiter(..) | Action::Embedded(..) => {
+                "delimiters"
+            }
+            Action::Symmetric(_) => "symmetric",
+            Action::OpenGeneric(..) | Action::CloseGeneric(..) => "generic",
+            Action::DoubledEscape => "escape",
+        }
+    }
+
+    /// Text of the marker, of which the pattern of the rule is only the first byte
+    fn marker(&self) -> Option<&'static str> {
+        match self {
+            Action::OpenMarker(marker, _)
+            | Action::MiddleMarker(marker, _)
+            | Action::CloseMarker(marker) => Some(marker),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the states are the same, ignoring the nesting depth of quote-like operators which
+/// rules don't match on
+fn same_state(a: State, b: State) -> bool {
+    match (a, b) {
+        (State::InBlockString(a, _), State::InBlockString(b, _)) => a == b,
+        (a, b) => a == b,
+    }
+}
+
+/// Section of the embedded delimiters matched in the state, see [`LanguageSpec::embedded`]
+fn embedded_section(state: State) -> Option<&'static str> {
+    match state {
+        State::InString(_) => Some("string"),
+        State::InBlockString(_, _) => Some("block_string"),
+        State::InLineComment => Some("line_comment"),
+        State::InBlockComment(_) => Some("block_comment"),
+        State::Normal => None,
+    }
+}
+
+impl Rule {
+    /// Text matched by the rule, which differs from the pattern for markers and quote-like
+    /// operators
+    fn text(&self) -> &[u8] {
+        match self.action {
+            Action::OpenQuote(open, _) => open.as_bytes(),
+            _ => self
+                .action
+                .marker()
+                .map_or(self.pattern, |marker| marker.as_bytes()),
+        }
+    }
+
+    fn is_conditional(&self) -> bool {
+        self.anchor.is_some()
+            || matches!(
+                self.action,
+                Action::Regex(..)
+                    | Action::OpenQuote(..)
+                    | Action::Char(_)
+                    | Action::OpenGeneric(..)
+                    | Action::CloseGeneric(..)
+            )
+    }
+
+    /// Whether this rule applies in the state, with embedded delimiters applying in the states
+    /// of the `embedded` sections
+    fn applies_in(&self, state: State, embedded: &[String]) -> bool {
+        match self.action {
+            Action::Embedded(..) => {
+                embedded_section(state).is_some_and(|section| embedded.iter().any(|s| s == section))
+            }
+            _ => same_state(self.input_state, state),
+        }
+    }
+
+    /// Whether this rule applies in every state the other rule applies in
+    fn covers_states(&self, other: &Rule, embedded: &[String]) -> bool {
+        match other.action {
+            Action::Embedded(..) => matches!(self.action, Action::Embedded(..)),
+            _ => self.applies_in(other.input_state, embedded),
+        }
+    }
+
+    /// Whether this rule applies in any of the states the other rule applies in
+    fn overlaps_states(&self, other: &Rule, embedded: &[String]) -> bool {
+        self.covers_states(other, embedded) || other.covers_states(self, embedded)
+    }
+
+    /// Whether this rule always applies when the other rule would, when placed before it
+    fn shadows(&self, other: &Rule, embedded: &[String]) -> bool {
+        self.text() == other.text()
+            && self.covers_states(other, embedded)
+            && !self.is_conditional()
+            && (!self.ignore_escaped || other.ignore_escaped)
+    }
+
+    /// Whether this rule sometimes applies when the other rule would, when placed before it,
+    /// depending on its conditions
+    fn conflicts_with(&self, other: &Rule, embedded: &[String]) -> bool {
+        self.text() == other.text()
+            && self.overlaps_states(other, embedded)
+            && self.is_conditional()
+    }
+}
+
+#[derive(Debug)]
+struct Rules {
+    /// Spec with the sections of the base language merged in
+    spec: LanguageSpec,
+    extends: Option<&'static str>,
+    tokens: &'static [u8],
+    max_lookahead: usize,
+    /// Line-limited symmetric delimiters and the closing generic angle brackets
+    same_line: &'static [&'static str],
+    /// Region markers found inside of line comments
+    regions: &'static [(&'static str, &'static str)],
+    /// Rules in priority order, indexed by the first byte of their pattern
+    by_byte: Box<[Vec<Rule>]>,
+}
+
+/// Matcher built from a [`LanguageSpec`] at runtime, behaving the same as the matchers
+/// generated by `define_matcher!`
+///
+/// The patterns are leaked to get the `&'static str` required by [`Token`] and [`State`], and
+/// interned so that creating the matcher again (i.e. when re-registering a language) reuses
+/// them. Matchers should still be created once rather than per parse
+#[derive(Debug, Clone)]
+pub struct DynamicMatcher {
+    rules: Arc<Rules>,
+    lookahead: Vec<(u8, usize)>,
+}
+
+type Interned<T> = LazyLock<Mutex<HashSet<&'static T>>>;
+
+static PATTERNS: Interned<str> = LazyLock::new(Default::default);
+static TOKENS: Interned<[u8]> = LazyLock::new(Default::default);
+static SAME_LINE: Interned<[&'static str]> = LazyLock::new(Default::default);
+static REGIONS: Interned<[(&'static str, &'static str)]> = LazyLock::new(Default::default);
+
+/// Leaks the value, unless an equal value has been leaked before
+fn intern<T>(interned: &Interned<T>, value: &T) -> &'static T
+where
+    T: ?Sized + Eq + Hash + ToOwned,
+    T::Owned: Into<Box<T>>,
+{
+    let mut interned = interned.lock().unwrap();
+    if let Some(value) = interned.get(value) {
+        return value;
+    }
+    let value: &'static T = Box::leak(value.to_owned().into());
+    interned.insert(value);
+    value
+}
+
+fn leak(str: &str) -> &'static str {
+    intern(&PATTERNS, str)
+}
+
+fn validate_pattern(section: &str, pattern: &str) -> Result<(), String> {
+    if pattern.is_empty() {
+        return Err(format!("{section}: patterns must not be empty"));
+    }
+    if pattern.contains('\n') {
+        return Err(format!("{section}: patterns must not contain newlines"));
+    }
+    Ok(())
+}
+
+fn validate_marker(section: &str, pattern: &str) -> Result<(), String> {
+    validate_pattern(section, pattern)?;
+    if !pattern.is_ascii() {
+        return Err(format!("{section}: \"{pattern}\" must be ASCII"));
+    }
+    Ok(())
+}
+
+fn validate_single_char(section: &str, pattern: &str) -> Result<(), String> {
+    validate_pattern(section, pattern)?;
+    if pattern.len() != 1 {
+        return Err(format!(
+            "{section}: \"{pattern}\" must be a single character"
+        ));
+    }
+    Ok(())
+}
+
+impl DynamicMatcher {
+    pub fn new(spec: &LanguageSpec) -> Result<Self, String> {
+        let extends = spec.extends.as_deref().map(leak);
+        let spec = &spec.resolve()?;
+        for open in spec.escape.keys() {
+            let is_string = spec.string.contains(open)
+                || spec
+                    .block_string
+                    .iter()
+                    .any(|(block_open, _)| block_open == open);
+            if !is_string {
+                return Err(format!(
+                    "escape: \"{open}\" doesn't refer to a string or block string"
+                ));
+            }
+        }
+
+        for section in &spec.continuation {
+            if section != "string" && section != "line_comment" {
+                return Err(format!(
+                    "continuation: expected \"string\" or \"line_comment\", got \"{section}\""
+                ));
+            }
+        }
+        for section in &spec.embedded {
+            if !["string", "block_string", "line_comment", "block_comment"]
+                .contains(&section.as_str())
+            {
+                return Err(format!(
+                    "embedded: expected \"string\", \"block_string\", \"line_comment\" or \"block_comment\", got \"{section}\""
+                ));
+            }
+        }
+
+        for (section, anchors) in &spec.anchor {
+            for pattern in anchors.keys() {
+                let pairs = |pairs: &[(String, String)]| {
+                    pairs
+                        .iter()
+                        .any(|(open, close)| open == pattern || close == pattern)
+                };
+                let found = match section.as_str() {
+                    "delimiters" => pairs(&spec.delimiters),
+                    "symmetric" => spec.symmetric.contains_key(pattern),
+                    "line_comment" => spec.line_comment.contains(pattern),
+                    "block_comment" => pairs(&spec.block_comment),
+                    "string" => spec.string.contains(pattern),
+                    "char" => spec.char.contains(pattern),
+                    "block_string" => pairs(&spec.block_string),
+                    "regex" => spec.regex.iter().any(|(open, _)| open == pattern),
+                    "marker" => pairs(&spec.marker),
+                    "middle" => spec.middle.iter().any(|(middle, _)| middle == pattern),
+                    _ => return Err(format!("anchor: unknown section \"{section}\"")),
+                };
+                if !found {
+                    return Err(format!(
+                        "anchor: \"{pattern}\" isn't a pattern of {section}"
+                    ));
+                }
+            }
+        }
+
+        let mut rules = vec![];
+        let mut rule = |pattern: &'static str, input_state, ignore_escaped, action: Action| {
+            let anchor = spec
+                .anchor
+                .get(action.section())
+                .and_then(|anchors| anchors.get(action.marker().unwrap_or(pattern)))
+                .copied();
+            rules.push(Rule {
+                pattern: pattern.as_bytes(),
+                input_state,
+                ignore_escaped,
+                anchor,
+                action,
+            })
+        };
+
+        // Same order as the arms generated by `define_matcher!`
+        let mut closes = vec![];
+        for (open, close) in &spec.marker {
+            validate_marker("marker", open)?;
+            validate_marker("marker", close)?;
+            let (open, close) = (leak(open), leak(close));
+            rule(
+                &open[..1],
+                State::Normal,
+                false,
+                Action::OpenMarker(open, close),
+            );
+            // Opening markers may share the same closing marker, i.e. `#if` and `#ifdef`
+            if !closes.contains(&close) {
+                closes.push(close);
+                rule(
+                    &close[..1],
+                    State::Normal,
+                    false,
+                    Action::CloseMarker(close),
+                );
+            }
+        }
+        for (middle, close) in &spec.middle {
+            validate_marker("middle", middle)?;
+            validate_marker("middle", close)?;
+            let (middle, close) = (leak(middle), leak(close));
+            rule(
+                &middle[..1],
+                State::Normal,
+                false,
+                Action::MiddleMarker(middle, close),
+            );
+        }
+        for (open, close) in &spec.block_comment {
+            validate_pattern("block_comment", open)?;
+            validate_pattern("block_comment", close)?;
+            let (open, close) = (leak(open), leak(close));
+            rule(
+                open,
+                State::Normal,
+                false,
+                Action::OpenBlockComment(open, close),
+            );
+            rule(
+                close,
+                State::InBlockComment(open),
+                false,
+                Action::CloseBlockComment(open, close),
+            );
+        }
+        for (open, close) in &spec.block_string {
+            validate_pattern("block_string", open)?;
+            validate_pattern("block_string", close)?;
+            let (open, close) = (leak(open), leak(close));
+            rule(
+                open,
+                State::Normal,
+                false,
+                Action::OpenBlockString(open, close),
+            );
+            let escape = spec.escape.get(open).copied().unwrap_or_default();
+            if escape == Escape::Doubled {
+                let doubled = leak(&close.repeat(2));
+                rule(
+                    doubled,
+                    State::InBlockString(open, 0),
+                    false,
+                    Action::DoubledEscape,
+                );
+            }
+            rule(
+                close,
+                State::InBlockString(open, 0),
+                escape == Escape::Backslash,
+                Action::CloseBlockString(open, close),
+            );
+        }
+        for comment in &spec.line_comment {
+            validate_pattern("line_comment", comment)?;
+            let comment = leak(comment);
+            rule(comment, State::Normal, false, Action::LineComment(comment));
+        }
+        for delim in &spec.string {
+            validate_pattern("string", delim)?;
+            let delim = leak(delim);
+            rule(delim, State::Normal, false, Action::OpenString(delim));
+            let escape = spec.escape.get(delim).copied().unwrap_or_default();
+            if escape == Escape::Doubled {
+                let doubled = leak(&delim.repeat(2));
+                rule(
+                    doubled,
+                    State::InString(delim),
+                    false,
+                    Action::DoubledEscape,
+                );
+            }
+            rule(
+                delim,
+                State::InString(delim),
+                escape == Escape::Backslash,
+                Action::CloseString(delim),
+            );
+        }
+        for (open, close) in &spec.regex {
+            validate_pattern("regex", open)?;
+            validate_pattern("regex", close)?;
+            let (open, close) = (leak(open), leak(close));
+            rule(open, State::Normal, false, Action::Regex(open, close));
+        }
+        for (prefix, &parts) in &spec.quote_like {
+            validate_pattern("quote_like", prefix)?;
+            if !(1..=2).contains(&parts) {
+                return Err(format!("quote_like: \"{prefix}\" must have 1 or 2 parts"));
+            }
+            for &(open_delim, close_delim) in QUOTE_LIKE_DELIMITERS {
+                let open = leak(&format!("{prefix}{open_delim}"));
+                let input_state = State::InBlockString(open, 0);
+                rule(
+                    open_delim,
+                    State::Normal,
+                    false,
+                    Action::OpenQuote(open, close_delim),
+                );
+                if open_delim != close_delim {
+                    rule(open_delim, input_state, true, Action::NestQuote);
+                    if parts == 1 {
+                        rule(
+                            close_delim,
+                            input_state,
+                            true,
+                            Action::CloseQuote(open, close_delim),
+                        );
+                        continue;
+                    }
+
+                    let between = leak(&format!("{open}{close_delim}"));
+                    rule(
+                        close_delim,
+                        input_state,
+                        true,
+                        Action::CloseQuoteFirstPart(open, close_delim, between),
+                    );
+                    let nested_delims = QUOTE_LIKE_DELIMITERS
+                        .iter()
+                        .filter(|(open, close)| open != close);
+                    for &(second_open, second_close) in nested_delims {
+                        let second = leak(&format!("{between}{second_open}"));
+                        rule(
+                            second_open,
+                            State::InBlockString(between, 0),
+                            false,
+                            Action::OpenQuoteSecondPart(second),
+                        );
+                        rule(
+                            second_open,
+                            State::InBlockString(second, 0),
+                            true,
+                            Action::NestQuote,
+                        );
+                        rule(
+                            second_close,
+                            State::InBlockString(second, 0),
+                            true,
+                            Action::CloseQuote(open, close_delim),
+                        );
+                    }
+                } else if parts == 2 {
+                    let middle = leak(&format!("{open}{close_delim}"));
+                    rule(close_delim, input_state, true, Action::QuoteMiddle(middle));
+                    rule(
+                        close_delim,
+                        State::InBlockString(middle, 0),
+                        true,
+                        Action::CloseQuote(open, close_delim),
+                    );
+                } else {
+                    rule(
+                        close_delim,
+                        input_state,
+                        true,
+                        Action::CloseQuote(open, close_delim),
+                    );
+                }
+            }
+        }
+        for delim in &spec.char {
+            validate_single_char("char", delim)?;
+            let delim = leak(delim);
+            rule(delim, State::Normal, false, Action::Char(delim));
+        }
+        for (open, close) in &spec.delimiters {
+            validate_pattern("delimiters", open)?;
+            validate_pattern("delimiters", close)?;
+            let (open, close) = (leak(open), leak(close));
+            rule(
+                open,
+                State::Normal,
+                false,
+                Action::OpenDelimiter(open, close),
+            );
+            rule(
+                close,
+                State::Normal,
+                false,
+                Action::CloseDelimiter(open, close),
+            );
+        }
+        let mut same_line = vec![];
+        for (delim, &limit) in &spec.symmetric {
+            validate_pattern("symmetric", delim)?;
+            let delim = leak(delim);
+            rule(delim, State::Normal, false, Action::Symmetric(delim));
+            if limit == Limit::Line {
+                same_line.push(delim);
+            }
+        }
+        for (open, close) in &spec.generic {
+            validate_single_char("generic", open)?;
+            validate_single_char("generic", close)?;
+            let (open, close) = (leak(open), leak(close));
+            rule(open, State::Normal, false, Action::OpenGeneric(open, close));
+            rule(
+                close,
+                State::Normal,
+                false,
+                Action::CloseGeneric(open, close),
+            );
+            same_line.push(close);
+        }
+        // The input state is ignored, embedded delimiters match in any state listed in
+        // `embedded`, see `DynamicMatcher::embedded_in`
+        if !spec.embedded.is_empty() {
+            for (open, close) in &spec.delimiters {
+                let (open, close) = (leak(open), leak(close));
+                rule(
+                    open,
+                    State::Normal,
+                    true,
+                    Action::Embedded(open, close, Kind::Opening),
+                );
+                rule(
+                    close,
+                    State::Normal,
+                    true,
+                    Action::Embedded(open, close, Kind::Closing),
+                );
+            }
+        }
+        let mut regions = vec![];
+        for (open, close) in &spec.region {
+            validate_pattern("region", open)?;
+            validate_pattern("region", close)?;
+            regions.push((leak(open), leak(close)));
+        }
+        // Longest match wins, i.e. `$$` takes precedence over `$` and `[|` over `[`
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.text().len()));
+        let embedded = &spec.embedded;
+        for (idx, rule) in rules.iter().enumerate() {
+            let earlier_rules = &rules[..idx];
+            let section = rule.action.section();
+            let text = String::from_utf8_lossy(rule.text());
+            if let Some(earlier) = earlier_rules
+                .iter()
+                .find(|earlier| earlier.shadows(rule, embedded))
+            {
+                return Err(format!(
+                    "{section}: \"{text}\" is unreachable, it's already matched by {}",
+                    earlier.action.section()
+                ));
+            }
+            if let Some(earlier) = earlier_rules.iter().find(|earlier| {
+                earlier.action.section() != section && earlier.conflicts_with(rule, embedded)
+            }) {
+                return Err(format!(
+                    "{section}: \"{text}\" is ambiguous, it's also matched by {}",
+                    earlier.action.section()
+                ));
+            }
+        }
+
+        let mut tokens = rules
+            .iter()
+            .flat_map(|rule| rule.pattern.iter().copied())
+            .collect::<Vec<_>>();
+        tokens.sort();
+        tokens.dedup();
+
+        let max_lookahead = rules
+            .iter()
+            .map(|rule| rule.pattern.len())
+            .max()
+            .unwrap_or(0)
+            .saturating_sub(1);
+
+        let mut by_byte = vec![vec![]; 256].into_boxed_slice();
+        for rule in rules {
+            by_byte[rule.pattern[0] as usize].push(rule);
+        }
+
+        Ok(Self {
+            rules: Arc::new(Rules {
+                spec: spec.clone(),
+                extends,
+                tokens: intern(&TOKENS, &tokens),
+                max_lookahead,
+                same_line: intern(&SAME_LINE, &same_line),
+                regions: intern(&REGIONS, &regions),
+                by_byte,
+            }),
+            lookahead: Vec::with_capacity(max_lookahead),
+        })
+    }
+
+    /// Whether delimiters are matched in the state, as embedded delimiters
+    fn embedded_in(&self, state: State) -> bool {
+        embedded_section(state)
+            .is_some_and(|section| self.rules.spec.embedded.iter().any(|s| s == section))
+    }
+
+    /// Whether the rule applies given the current token, state and lookahead tokens
+    fn rule_matches(
+        &self,
+        rule: &Rule,
+        state: State,
+        token: CharPos,
+        escaped: bool,
+        line: &[u8],
+    ) -> bool {
+        let accepts_state = match rule.action {
+            Action::Embedded(..) => self.embedded_in(state),
+            _ => same_state(rule.input_state, state),
+        };
+        if !accepts_state || (rule.ignore_escaped && escaped) {
+            return false;
+        }
+        if let Some(anchor) = rule.anchor {
+            if !anchor.matches(line, token.col, rule.text().len()) {
+                return false;
+            }
+        }
+        if let Some(marker) = rule.action.marker() {
+            return marker_at(line, token.col, marker);
+        }
+
+        // The rest of the pattern must be found in the adjacent tokens
+        let adjacent = rule.pattern[1..]
+            .iter()
+            .zip(&self.lookahead)
+            .enumerate()
+            .all(|(idx, (&expected, &(byte, distance)))| byte == expected && distance == idx + 1);
+        adjacent
+            && match rule.action {
+                Action::Regex(open, close) => {
+                    regex_literal_len(line, token.col, open, close).is_some()
+                }
+                Action::OpenQuote(open, _) => {
+                    let prefix = &open.as_bytes()[..open.len() - rule.pattern.len()];
+                    line[..token.col].ends_with(prefix)
+                        && quote_like_start(line, token.col - prefix.len())
+                }
+                Action::OpenGeneric(..) => opens_generic(line, token.col),
+                Action::CloseGeneric(..) => closes_generic(line, token.col),
+                Action::Char(delim) => {
+                    char_literal_len(line, token.col, delim.as_bytes()[0]).is_some()
+                }
+                _ => true,
+            }
+    }
+}
+
+impl Matcher for DynamicMatcher {
+    const TOKENS: &[u8] = &[];
+
+    fn tokens(&self) -> &'static [u8] {
+        self.rules.tokens
+    }
+
+    fn describe(&self) -> LanguageSpec {
+        self.rules.spec.clone()
+    }
+
+    fn metadata(&self) -> MatcherMetadata {
+        MatcherMetadata {
+            name: "Dynamic",
+            extends: self.rules.extends,
+        }
+    }
+
+    fn continues_line(&self, state: State) -> bool {
+        let section = match state {
+            State::InString(_) => "string",
+            State::InLineComment => "line_comment",
+            _ => return false,
+        };
+        self.rules.spec.continuation.iter().any(|s| s == section)
+    }
+
+    fn same_line_delimiters(&self) -> &'static [&'static str] {
+        self.rules.same_line
+    }
+
+    fn call<I>(
+        &mut self,
+        matches: &mut Vec<Match>,
+        stack: &mut Vec<&'static str>,
+        tokens: &mut MultiPeek<I>,
+        state: State,
+        token: CharPos,
+        escaped: bool,
+        line: &[u8],
+    ) -> State
+    where
+        I: Iterator<Item = CharPos>,
+    {
+        // Lookahead tokens, ignoring everything after a newline
+        self.lookahead.clear();
+        let mut found_new_line = false;
+        for _ in 0..self.rules.max_lookahead {
+            let next_token = tokens.peek().filter(|t| {
+                found_new_line |= t.byte == b'\n';
+                !found_new_line
+            });
+            self.lookahead.push((
+                next_token.map(|t| t.byte).unwrap_or(0),
+                next_token.map(|t| t.col).unwrap_or(usize::MAX) - token.col,
+            ));
+        }
+
+        let Some(rule) = self.rules.by_byte[token.byte as usize]
+            .iter()
+            .find(|rule| self.rule_matches(rule, state, token, escaped, line))
+        else {
+            return state;
+        };
+
+        // Skip tokens based on length of pattern
+        let skip = |tokens: &mut MultiPeek<I>, count: usize| {
+            for _ in 0..count {
+                tokens.next();
+            }
+        };
+
+        match rule.action {
+            Action::OpenMarker(open, close) => {
+                matches.push(Match::new(
+                    Kind::Opening,
+                    Token::Marker(open, close),
+                    token.col,
+                ));
+                skip_to_col(tokens, token.col + open.len());
+                State::Normal
+            }
+            Action::MiddleMarker(middle, close) => {
+                matches.push(Match::new(
+                    Kind::Middle,
+                    Token::Marker(middle, close),
+                    token.col,
+                ));
+                skip_to_col(tokens, token.col + middle.len());
+                State::Normal
+            }
+            Action::CloseMarker(close) => {
+                matches.push(Match::new(
+                    Kind::Closing,
+                    Token::Marker(close, close),
+                    token.col,
+                ));
+                skip_to_col(tokens, token.col + close.len());
+                State::Normal
+            }
+            Action::OpenBlockComment(open, close) => {
+                matches.push(Match::new(
+                    Kind::Opening,
+                    Token::BlockComment(open, close),
+                    token.col,
+                ));
+                skip(tokens, open.len() - 1);
+                State::InBlockComment(open)
+            }
+            Action::CloseBlockComment(open, close) => {
+                matches.push(Match::new(
+                    Kind::Closing,
+                    Token::BlockComment(open, close),
+                    token.col,
+                ));
+                skip(tokens, close.len() - 1);
+                State::Normal
+            }
+            Action::OpenBlockString(open, close) => {
+                matches.push(Match::new(
+                    Kind::Opening,
+                    Token::BlockString(open, close),
+                    token.col,
+                ));
+                skip(tokens, open.len() - 1);
+                State::InBlockString(open, 0)
+            }
+            Action::CloseBlockString(open, close) => {
+                matches.push(Match::new(
+                    Kind::Closing,
+                    Token::BlockString(open, close),
+                    token.col,
+                ));
+                skip(tokens, close.len() - 1);
+                State::Normal
+            }
+            Action::LineComment(comment) => {
+                matches.push(Match::line_comment(comment, token.col));
+                if let Some(region) = region_in_comment(line, token.col, self.rules.regions) {
+                    matches.push(region);
+                }
+                skip(tokens, comment.len() - 1);
+                State::InLineComment
+            }
+            Action::OpenString(delim) => {
+                matches.push(Match::new(Kind::Opening, Token::String(delim), token.col));
+                skip(tokens, delim.len() - 1);
+                State::InString(delim)
+            }
+            Action::CloseString(delim) => {
+                matches.push(Match::new(Kind::Closing, Token::String(delim), token.col));
+                skip(tokens, delim.len() - 1);
+                State::Normal
+            }
+            Action::DoubledEscape => {
+                skip(tokens, rule.pattern.len() - 1);
+                state
+            }
+            Action::Embedded(open, close, kind) => {
+                matches.push(Match::new(kind, Token::Embedded(open, close), token.col));
+                skip(tokens, rule.pattern.len() - 1);
+                state
+            }
+            Action::Regex(open, close) => {
+                let len = regex_literal_len(line, token.col, open, close).unwrap();
+                matches.push(Match::new(
+                    Kind::Opening,
+                    Token::BlockString(open, close),
+                    token.col,
+                ));
+                matches.push(Match::new(
+                    Kind::Closing,
+                    Token::BlockString(open, close),
+                    token.col + len - close.len(),
+                ));
+                skip_to_col(tokens, token.col + len);
+                State::Normal
+            }
+            Action::OpenQuote(open, close) => {
+                matches.push(Match::new(
+                    Kind::Opening,
+                    Token::BlockString(open, close),
+                    token.col + rule.pattern.len() - open.len(),
+                ));
+                skip(tokens, rule.pattern.len() - 1);
+                State::InBlockString(open, 0)
+            }
+            Action::NestQuote => match state {
+                State::InBlockString(open, depth) => State::InBlockString(open, depth + 1),
+                state => state,
+            },
+            Action::QuoteMiddle(middle) => {
+                skip(tokens, rule.pattern.len() - 1);
+                State::InBlockString(middle, 0)
+            }
+            Action::OpenQuoteSecondPart(second) => {
+                skip(tokens, rule.pattern.len() - 1);
+                State::InBlockString(second, 0)
+            }
+            Action::CloseQuote(open, close) | Action::CloseQuoteFirstPart(open, close, _) => {
+                if let State::InBlockString(state_open, depth @ 1..) = state {
+                    return State::InBlockString(state_open, depth - 1);
+                }
+                if let Action::CloseQuoteFirstPart(_, _, between) = rule.action {
+                    if quote_like_second_part(line, token.col + close.len()) {
+                        return State::InBlockString(between, 0);
+                    }
+                }
+                matches.push(Match::new(
+                    Kind::Closing,
+                    Token::BlockString(open, close),
+                    token.col,
+                ));
+                skip(tokens, close.len() - 1);
+                State::Normal
+            }
+            Action::Char(delim) => {
+                let len = char_literal_len(line, token.col, delim.as_bytes()[0]).unwrap();
+                matches.push(Match::new(Kind::Opening, Token::String(delim), token.col));
+                matches.push(Match::new(
+                    Kind::Closing,
+                    Token::String(delim),
+                    token.col + len - 1,
+                ));
+                skip_to_col(tokens, token.col + len);
+                State::Normal
+            }
+            Action::OpenDelimiter(open, close) => {
+                matches.push(Match::new_with_stack(
+                    Kind::Opening,
+                    Token::Delimiter(open, close),
+                    token.col,
+                    stack.len(),
+                ));
+                stack.push(close);
+                skip(tokens, open.len() - 1);
+                State::Normal
+            }
+            Action::CloseDelimiter(open, close) => {
+                unwind_symmetric(matches, stack, close, self.rules.same_line);
+                if stack.last() == Some(&close) {
+                    stack.pop();
+                }
+                matches.push(Match::new_with_stack(
+                    Kind::Closing,
+                    Token::Delimiter(open, close),
+                    token.col,
+                    stack.len(),
+                ));
+                skip(tokens, close.len() - 1);
+                State::Normal
+            }
+            Action::Symmetric(delim) => {
+                unwind_symmetric(matches, stack, delim, self.rules.same_line);
+                let kind = if stack.last() == Some(&delim) {
+                    stack.pop();
+                    Kind::Closing
+                } else {
+                    Kind::Opening
+                };
+                matches.push(Match::new_with_stack(
+                    kind,
+                    Token::Delimiter(delim, delim),
+                    token.col,
+                    stack.len(),
+                ));
+                if kind == Kind::Opening {
+                    stack.push(delim);
+                }
+                skip(tokens, delim.len() - 1);
+                State::Normal
+            }
+            Action::OpenGeneric(open, close) => {
+                matches.push(Match::new_with_stack(
+                    Kind::Opening,
+                    Token::Delimiter(open, close),
+                    token.col,
+                    stack.len(),
+                ));
+                stack.push(close);
+                State::Normal
+            }
+            Action::CloseGeneric(open, close) => {
+                unwind_symmetric(matches, stack, close, self.rules.same_line);
+                if stack.last() == Some(&close) {
+                    stack.pop();
+                    matches.push(Match::new_with_stack(
+                        Kind::Closing,
+                        Token::Delimiter(open, close),
+                        token.col,
+                        stack.len(),
+                    ));
+                }
+                State::Normal
+            }
+        }
+    }
+}
+
+static LANGUAGES: LazyLock<RwLock<HashMap<String, DynamicMatcher>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Registers a language for the filetype, taking precedence over the built-in languages.
+/// Registering the same filetype again replaces the previous definition
+pub fn register_language(filetype: &str, spec: &LanguageSpec) -> Result<(), String> {
+    let matcher = DynamicMatcher::new(spec)?;
+    LANGUAGES
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(filetype.to_string(), matcher);
+    Ok(())
+}
+
+pub fn is_registered(filetype: &str) -> bool {
+    LANGUAGES
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .contains_key(filetype)
+}
+
+pub fn registered_filetypes() -> Vec<String> {
+    LANGUAGES
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .keys()
+        .cloned()
+        .collect()
+}
+
+pub fn registered_language(filetype: &str) -> Option<DynamicMatcher> {
+    LANGUAGES
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(filetype)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{builtin_spec, languages, parse, parse_builtin, BUILTIN_FILETYPES};
+
+    fn spec(
+        delimiters: &[(&str, &str)],
+        line_comment: &[&str],
+        block_comment: &[(&str, &str)],
+        string: &[&str],
+        char: &[&str],
+        block_string: &[(&str, &str)],
+    ) -> LanguageSpec {
+        let pairs = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(open, close)| (open.to_string(), close.to_string()))
+                .collect()
+        };
+        let strings = |strings: &[&str]| strings.iter().map(|s| s.to_string()).collect();
+
+        LanguageSpec {
+            delimiters: pairs(delimiters),
+            line_comment: strings(line_comment),
+            block_comment: pairs(block_comment),
+            string: strings(string),
+            char: strings(char),
+            block_string: pairs(block_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_builtin_languages() {
+        let corpora = [
+            include_str!("../../benches/languages/c.c"),
+            include_str!("../../benches/languages/rust.rs"),
+            include_str!("../../benches/languages/perl.pl"),
+            // Escapes
+            r##"@"a""b" 'it''s' r#"\"# `\` "\"" ()"##,
+            // Char literals
+            r"'\u{1F600}' '\'' '(' 1'000 0b1'0'1 0xF'F'FF u8'(' f x' 'a ' '",
+            // Regex literals
+            r#"x = /[(]"/g; (a / b / c) %r{a{2}(} <a>x</a><b>y</b>"#,
+            // Quote-like operators
+            "(q{a {b} \\} ( \\{}) s/(/)/; $s/2 %w[a (\nb] + [1] %q{a} ~S\"\"\"\n\")\n\"\"\"",
+            "s{a}{(b)}; s(a) [b] tr{a}\n {b} y<a>/b/",
+            "q{a {\nb (\n} c} (d) s{\n{a}\n} {\n{b}\n}",
+            // Anchors
+            "=begin ()\n=pod $# a#b # ()\n=end =cut\n x =begin",
+            // Markers
+            "#ifdef A {\n  #elif B\n#else {\n#endif }\n#ifdefined x #if",
+            // Symmetric delimiters
+            "|x| (a | [b]) $a * b$ *c* {\nA | B => $\n$ | 1 *",
+            // Generic angle brackets
+            "Vec<HashMap<K, V>> (i<n) a < b > c x->y<T>::z template <T> a<<b |a<b| Vec<\n>",
+            // Git conflict markers
+            "<<<<<<< HEAD\n(\"a\n||||||| base\n=======\n[ /*\n>>>>>>> b\n) =======",
+            // Region markers
+            "// #region a {{{1\n# region\n-- #endregion }}}\n#regional // {{{\n#pragma region {\n#region\n#endregion\n#pragma endregion",
+            // Line continuations
+            "// a \\\n(b) 'c \\\n)' \"d \\\\\n( \\",
+        ];
+
+        for filetype in BUILTIN_FILETYPES {
+            let spec = builtin_spec(filetype).unwrap();
+            #[cfg(feature = "spec")]
+            assert_eq!(
+                LanguageSpec::from_toml(&spec.to_toml().unwrap()).unwrap(),
+                spec,
+                "{filetype} does not round-trip through TOML"
+            );
+            let matcher = DynamicMatcher::new(&spec).unwrap();
+
+            for text in corpora {
+                let lines = text.lines().collect::<Vec<_>>();
+                assert_eq!(
+                    parse(&lines, State::Normal, matcher.clone()),
+                    parse_builtin(filetype, &lines, State::Normal).unwrap(),
+                    "{filetype} differs from the built-in matcher"
+                );
+            }
+        }
+    }
+
+    mod embedded {
+        use crate::parser::*;
+        use matcher_macros::define_matcher;
+
+        define_matcher!(EmbeddedRust {
+            extends: Rust,
+            embedded: [block_string, line_comment]
+        });
+    }
+
+    #[test]
+    fn test_embedded() {
+        let spec = LanguageSpec {
+            extends: Some("rust".to_string()),
+            embedded: vec!["block_string".to_string(), "line_comment".to_string()],
+            ..Default::default()
+        };
+        let lines = [r#"f("(\d+)\(", x); // g(x) {"#, "(a)"];
+        let (matches, _) = parse(&lines, State::Normal, DynamicMatcher::new(&spec).unwrap());
+        assert_eq!(
+            (matches.clone(), vec![State::Normal; 2]),
+            parse(&lines, State::Normal, embedded::EmbeddedRust)
+        );
+        assert_eq!(
+            matches[0]
+                .iter()
+                .filter(|m| matches!(m.token, Token::Embedded(..)))
+                .map(|m| (m.kind, m.col))
+                .collect::<Vec<_>>(),
+            vec![
+                (Kind::Opening, 3),
+                (Kind::Closing, 7),
+                (Kind::Opening, 21),
+                (Kind::Closing, 23),
+                (Kind::Opening, 25),
+            ]
+        );
+
+        // The unclosed `{` in the comment doesn't affect the heights of the delimiters
+        register_language("test-embedded", &spec).unwrap();
+        let buffer = crate::buffer::ParsedBuffer::parse("test-embedded", &lines).unwrap();
+        let heights = |line: usize| {
+            buffer
+                .line_matches(line)
+                .unwrap()
+                .iter()
+                .filter_map(|m| m.stack_height)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(heights(0), vec![0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(heights(1), vec![0, 0]);
+        let (open, close) = buffer.match_pair(0, 3).unwrap();
+        assert_eq!((open.col, close.col), (3, 7));
+    }
+
+    #[test]
+    fn test_extends() {
+        assert_eq!(
+            languages::Cpp.metadata(),
+            MatcherMetadata {
+                name: "Cpp",
+                extends: Some("C"),
+            }
+        );
+        let c = builtin_spec("c").unwrap();
+        let mut anchor = c.anchor.clone();
+        anchor.remove("char");
+        assert_eq!(
+            builtin_spec("objc").unwrap(),
+            LanguageSpec {
+                char: vec![],
+                anchor,
+                ..c.clone()
+            }
+        );
+
+        let spec = LanguageSpec {
+            extends: Some("c".to_string()),
+            without: vec!["block_comment".to_string()],
+            line_comment: vec!["#".to_string()],
+            ..Default::default()
+        };
+        let matcher = DynamicMatcher::new(&spec).unwrap();
+        assert_eq!(matcher.metadata().extends, Some("c"));
+        assert_eq!(matcher.describe().line_comment, vec!["//", "#"]);
+        assert_eq!(matcher.describe().block_comment, vec![]);
+
+        let spec = LanguageSpec {
+            extends: Some("unknown".to_string()),
+            ..Default::default()
+        };
+        assert!(DynamicMatcher::new(&spec).is_err());
+    }
+
+    #[test]
+    fn test_language_spec() {
+        let block_strings = |filetype| crate::parser::language_spec(filetype).unwrap().block_string;
+        let pairs = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(open, close)| (open.to_string(), close.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            block_strings("python"),
+            pairs(&[("\"\"\"", "\"\"\""), ("'''", "'''")])
+        );
+        assert_eq!(
+            block_strings("rust"),
+            pairs(&[
+                ("\"", "\""),
+                ("r#\"", "\"#"),
+                ("r##\"", "\"##"),
+                ("r###\"", "\"###"),
+            ])
+        );
+        assert!(crate::parser::language_spec("unknown").is_none());
+    }
+
+    #[cfg(feature = "spec")]
+    #[test]
+    fn test_load_spec() {
+        let toml = LanguageSpec::from_toml(
+            r#"
+            delimiters = [["(", ")"]]
+            line_comment = [";"]
+            "#,
+        )
+        .unwrap();
+        let json =
+            LanguageSpec::from_json(r#"{ "delimiters": [["(", ")"]], "line_comment": [";"] }"#)
+                .unwrap();
+        assert_eq!(toml, spec(&[("(", ")")], &[";"], &[], &[], &[], &[]));
+        assert_eq!(json, toml);
+
+        assert!(LanguageSpec::from_toml("comments = [\";\"]").is_err());
+    }
+
+    #[test]
+    fn test_reuses_leaked_patterns() {
+        let lisp = spec(&[("(", ")")], &[";"], &[("#|", "|#")], &["\""], &[], &[]);
+        let (first, second) = (
+            DynamicMatcher::new(&lisp).unwrap(),
+            DynamicMatcher::new(&lisp).unwrap(),
+        );
+        assert!(std::ptr::eq(first.tokens(), second.tokens()));
+        let pattern = |matcher: &DynamicMatcher| matcher.rules.by_byte[b'#' as usize][0].pattern;
+        assert!(std::ptr::eq(pattern(&first), pattern(&second)));
+    }
+
+    #[test]
+    fn test_register_language() {
+        let lisp = spec(&[("(", ")")], &[";"], &[("#|", "|#")], &["\""], &[], &[]);
+        register_language("test-lisp", &lisp).unwrap();
+
+        let (matches, states) = crate::parser::parse_filetype(
+            "test-lisp",
+            &["(a #| ( |#", "; )", "\")\")"],
+            State::Normal,
+        )
+        .unwrap();
+        assert_eq!(matches[0].len(), 3);
+        assert_eq!(matches[1], vec![Match::line_comment(";", 0)]);
+        assert_eq!(matches[2].len(), 3);
+        assert_eq!(states, vec![State::Normal; 3]);
+
+        let jinja = spec(&[("{", "}"), ("{%", "%}")], &[], &[], &[], &[], &[]);
+        register_language("test-jinja", &jinja).unwrap();
+        let (matches, _) =
+            crate::parser::parse_filetype("test-jinja", &["{% { } %}"], State::Normal).unwrap();
+        assert_eq!(
+            matches[0]
+                .iter()
+                .map(|m| (m.token.opening(), m.kind, m.col, m.stack_height))
+                .collect::<Vec<_>>(),
+            vec![
+                ("{%", Kind::Opening, 0, Some(0)),
+                ("{", Kind::Opening, 3, Some(1)),
+                ("{", Kind::Closing, 5, Some(1)),
+                ("{%", Kind::Closing, 7, Some(0)),
+            ]
+        );
+
+        let invalid = spec(&[("(", "")], &[], &[], &[], &[], &[]);
+        assert!(register_language("test-invalid", &invalid).is_err());
+        let unreachable = spec(&[("|", "|")], &[], &[], &[], &[], &[]);
+        assert!(register_language("test-unreachable", &unreachable).is_err());
+        let mut ambiguous = spec(&[], &[], &[], &[], &[], &[]);
+        ambiguous.regex = vec![("/".to_string(), "/".to_string())];
+        ambiguous.symmetric = BTreeMap::from([("/".to_string(), Limit::Line)]);
+        assert!(register_language("test-ambiguous", &ambiguous).is_err());
+
+        // Markers take precedence over shorter delimiters starting with the same byte
+        let mut handlebars = spec(&[("{{", "}}")], &[], &[], &[], &[], &[]);
+        handlebars.marker = vec![("{{#if".to_string(), "{{/if".to_string())];
+        register_language("test-handlebars", &handlebars).unwrap();
+        let (matches, _) = crate::parser::parse_filetype(
+            "test-handlebars",
+            &["{{#if a}}{{b}}{{/if}}"],
+            State::Normal,
+        )
+        .unwrap();
+        assert_eq!(
+            matches[0]
+                .iter()
+                .map(|m| (m.token.opening(), m.kind, m.col))
+                .collect::<Vec<_>>(),
+            vec![
+                ("{{#if", Kind::Opening, 0),
+                ("{{", Kind::Closing, 7),
+                ("{{", Kind::Opening, 9),
+                ("{{", Kind::Closing, 12),
+                ("{{/if", Kind::Closing, 14),
+                ("{{", Kind::Closing, 19),
+            ]
+        );
+    }
+}
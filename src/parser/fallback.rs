@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use super::{DynamicMatcher, LanguageSpec};
+
+static FALLBACKS: LazyLock<Mutex<HashMap<String, DynamicMatcher>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Spec for filetypes without a language, guessing as little as possible:
+/// - `()`, `[]` and `{}` are always paired
+/// - line comments are taken from the `commentstring` (i.e. `# %s`). Block comments are
+///   ignored since they would span many lines when guessed wrong
+/// - `"` is a string, which always ends at the end of the line, and `'` is only a char literal
+///   when closed right after (i.e. `'a'`), so that apostrophes don't start strings
+pub fn fallback_spec(commentstring: &str) -> LanguageSpec {
+    let line_comment = match commentstring.split_once("%s") {
+        Some((prefix, suffix)) if suffix.trim().is_empty() => Some(prefix.trim()),
+        None => Some(commentstring.trim()),
+        _ => None,
+    }
+    .filter(|comment| !comment.is_empty() && !comment.contains('\n'));
+
+    LanguageSpec {
+        delimiters: [("(", ")"), ("[", "]"), ("{", "}")]
+            .map(|(open, close)| (open.to_string(), close.to_string()))
+            .to_vec(),
+        line_comment: line_comment.into_iter().map(str::to_string).collect(),
+        string: vec!["\"".to_string()],
+        char: vec!["'".to_string()],
+        ..Default::default()
+    }
+}
+
+/// Matcher for the [`fallback_spec`], cached per `commentstring`
+pub fn fallback_matcher(commentstring: &str) -> DynamicMatcher {
+    FALLBACKS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .entry(commentstring.to_string())
+        .or_insert_with(|| {
+            DynamicMatcher::new(&fallback_spec(commentstring))
+                .expect("fallback spec should always be valid")
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse, Match, State};
+
+    #[test]
+    fn test_fallback_spec() {
+        assert_eq!(fallback_spec("# %s").line_comment, vec!["#"]);
+        assert_eq!(fallback_spec("//%s").line_comment, vec!["//"]);
+        assert_eq!(fallback_spec("/* %s */").line_comment, Vec::<String>::new());
+        assert_eq!(fallback_spec("").line_comment, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_fallback_matcher() {
+        let lines = ["don't (a) # ) [", "\"(\" {"];
+        let (matches, states) = parse(&lines, State::Normal, fallback_matcher("# %s"));
+        assert_eq!(
+            matches[0],
+            vec![
+                Match::delimiter('(', 6, Some(0)),
+                Match::delimiter(')', 8, Some(0)),
+                Match::line_comment("#", 10),
+            ]
+        );
+        assert_eq!(matches[1].len(), 3);
+        assert_eq!(states, vec![State::Normal; 2]);
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use super::{dynamic, BUILTIN_FILETYPES};
+
+/// Filetypes (Neovim filetypes and LSP language identifiers) handled by a language of another name
+pub const ALIASES: &[(&str, &str)] = &[
+    ("bash", "shell"),
+    ("cs", "csharp"),
+    ("javascriptreact", "javascript"),
+    ("json5", "json"),
+    ("jsonc", "json"),
+    ("ksh", "shell"),
+    ("objective-c", "objc"),
+    ("objective-cpp", "objcpp"),
+    ("ocamlinterface", "ocaml"),
+    ("plaintex", "latex"),
+    ("sbt", "scala"),
+    ("sh", "shell"),
+    ("shellscript", "shell"),
+    ("tex", "latex"),
+    ("zsh", "shell"),
+];
+
+pub fn alias(filetype: &str) -> Option<&'static str> {
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == filetype)
+        .map(|(_, language)| *language)
+}
+
+/// Resolves the filetype to the name of a registered or built-in language, following aliases.
+/// Dotted filetypes (i.e. `javascript.jsx`) resolve to their first supported component
+pub fn resolve(filetype: &str) -> Option<&str> {
+    std::iter::once(filetype)
+        .chain(filetype.split('.').filter(|_| filetype.contains('.')))
+        .find_map(|filetype| {
+            if dynamic::is_registered(filetype) || BUILTIN_FILETYPES.contains(&filetype) {
+                Some(filetype)
+            } else {
+                alias(filetype)
+            }
+        })
+}
+
+/// Detects the filetype from the extension of the path, falling back to the shebang on the
+/// first line, for use outside of Neovim
+pub fn detect(path: &str, first_line: &str) -> Option<&'static str> {
+    from_path(path).or_else(|| from_shebang(first_line))
+}
+
+/// Detects the filetype from the interpreter of a shebang line, i.e. `#!/usr/bin/env python3`
+pub fn from_shebang(line: &str) -> Option<&'static str> {
+    let mut args = line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = args.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = args.find(|arg| !arg.starts_with('-'))?;
+    }
+    // Strip versions, i.e. python3.12
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    Some(match interpreter {
+        "sh" | "bash" | "dash" | "ksh" | "zsh" => "shell",
+        "python" => "python",
+        "node" | "nodejs" | "deno" | "bun" => "javascript",
+        "ruby" => "ruby",
+        "perl" => "perl",
+        "lua" | "luajit" => "lua",
+        "php" => "php",
+        "elixir" => "elixir",
+        "escript" => "erlang",
+        "Rscript" => "r",
+        "runghc" | "runhaskell" => "haskell",
+        "scala" => "scala",
+        _ => return None,
+    })
+}
+
+/// Detects the filetype from the extension of the path
+pub fn from_path(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    Some(match extension {
+        "c" | "h" => "c",
+        "clj" | "cljs" | "cljc" | "edn" => "clojure",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "cu" | "cuh" => "cuda",
+        "dart" => "dart",
+        "ex" | "exs" => "elixir",
+        "erl" | "hrl" => "erlang",
+        "fs" | "fsi" | "fsx" => "fsharp",
+        "glsl" | "vert" | "frag" | "geom" | "comp" | "tesc" | "tese" => "glsl",
+        "go" => "go",
+        "hs" => "haskell",
+        "hx" => "haxe",
+        "hlsl" | "hlsli" => "hlsl",
+        "java" => "java",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "typescriptreact",
+        "json" => "json",
+        "jsonc" => "jsonc",
+        "json5" => "json5",
+        "kt" | "kts" => "kotlin",
+        "tex" => "latex",
+        "lean" => "lean",
+        "lua" => "lua",
+        "m" => "objc",
+        "mm" => "objcpp",
+        "ml" | "mli" => "ocaml",
+        "pl" | "pm" => "perl",
+        "php" => "php",
+        "py" => "python",
+        "r" | "R" => "r",
+        "rb" => "ruby",
+        "rs" => "rust",
+        "scala" | "sc" | "sbt" => "scala",
+        "sh" | "bash" | "ksh" | "zsh" => "shell",
+        "sql" => "sql",
+        "swift" => "swift",
+        "toml" => "toml",
+        "typ" => "typst",
+        "yaml" | "yml" => "yaml",
+        "zig" => "zig",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve("rust"), Some("rust"));
+        assert_eq!(resolve("zsh"), Some("shell"));
+        assert_eq!(resolve("typescriptreact"), Some("typescriptreact"));
+        assert_eq!(resolve("javascriptreact"), Some("javascript"));
+        assert_eq!(resolve("javascript.jsx"), Some("javascript"));
+        assert_eq!(resolve("jsx.tex"), Some("latex"));
+        assert_eq!(resolve("unknown"), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("src/main.rs", ""), Some("rust"));
+        assert_eq!(detect("bin/script", "#!/bin/bash"), Some("shell"));
+        assert_eq!(
+            detect("bin/script", "#!/usr/bin/env -S python3.12 -u"),
+            Some("python")
+        );
+        assert_eq!(detect("bin/script", "echo"), None);
+    }
+}
@@ -9,7 +9,22 @@ define_matcher!(C {
     ],
     line_comment: ["//"],
     block_comment: ["/*" => "*/"],
-    char: ["'"],
+    // Not a char literal in digit separators, i.e. `0b1'0'1`
+    char: ["'" @ outside_number],
     string: ["\""],
-    block_string: []
+    block_string: [],
+    continuation: [string, line_comment],
+    marker: [
+        "#if" @ indent => "#endif" @ indent,
+        "#ifdef" @ indent => "#endif" @ indent,
+        "#ifndef" @ indent => "#endif" @ indent,
+        "#pragma region" @ indent => "#pragma endregion" @ indent
+    ],
+    middle: [
+        "#elif" @ indent => "#endif",
+        "#elifdef" @ indent => "#endif",
+        "#elifndef" @ indent => "#endif",
+        "#else" @ indent => "#endif"
+    ],
+    region: ["#region" => "#endregion", "{{{" => "}}}"]
 });
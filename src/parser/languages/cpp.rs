@@ -2,14 +2,7 @@ use crate::parser::*;
 use matcher_macros::define_matcher;
 
 define_matcher!(Cpp {
-    delimiters: [
-        "(" => ")",
-        "[" => "]",
-        "{" => "}"
-    ],
-    line_comment: ["//"],
-    block_comment: ["/*" => "*/"],
-    char: ["'"],
-    string: ["\""],
+    extends: C,
+    generic: ["<" => ">"],
     block_string: ["R\"(" => ")\""]
 });
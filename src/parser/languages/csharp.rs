@@ -2,14 +2,9 @@ use crate::parser::*;
 use matcher_macros::define_matcher;
 
 define_matcher!(CSharp {
-    delimiters: [
-        "(" => ")",
-        "[" => "]",
-        "{" => "}"
-    ],
-    line_comment: ["//"],
-    block_comment: ["/*" => "*/"],
-    char: ["'"],
-    string: ["\""],
-    block_string: ["@\"" => "\""]
+    extends: C,
+    without: [continuation],
+    marker: ["#region" @ indent => "#endregion" @ indent],
+    block_string: ["@\"" => "\""],
+    escape: ["@\"" => doubled]
 });
@@ -0,0 +1,4 @@
+use crate::parser::*;
+use matcher_macros::define_matcher;
+
+define_matcher!(Cuda { extends: Cpp });
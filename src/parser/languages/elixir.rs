@@ -9,5 +9,16 @@ define_matcher!(Elixir {
     ],
     line_comment: ["#"],
     string: ["\""],
-    block_string: ["\"\"\"" => "\"\"\""]
+    block_string: ["\"\"\"" => "\"\"\""],
+    // Sigils
+    quote_like: [
+        "~r" => 1,
+        "~R" => 1,
+        "~s" => 1,
+        "~S" => 1,
+        "~w" => 1,
+        "~W" => 1,
+        "~c" => 1,
+        "~C" => 1
+    ]
 });
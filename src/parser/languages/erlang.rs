@@ -5,7 +5,8 @@ define_matcher!(Erlang {
     delimiters: [
         "(" => ")",
         "[" => "]",
-        "{" => "}"
+        "{" => "}",
+        "<<" => ">>"
     ],
     line_comment: ["%"],
     string: ["\""]
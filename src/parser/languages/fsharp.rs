@@ -10,5 +10,7 @@ define_matcher!(FSharp {
     line_comment: ["//"],
     block_comment: ["(*" => "*)"],
     string: ["\""],
+    // Not a char literal in `x'`
+    char: ["'" @ word_start],
     block_string: ["\"\"\"" => "\"\"\""]
 });
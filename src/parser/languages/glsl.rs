@@ -0,0 +1,7 @@
+use crate::parser::*;
+use matcher_macros::define_matcher;
+
+define_matcher!(Glsl {
+    extends: C,
+    without: [char, string]
+});
@@ -2,14 +2,8 @@ use crate::parser::*;
 use matcher_macros::define_matcher;
 
 define_matcher!(Java {
-    delimiters: [
-        "(" => ")",
-        "[" => "]",
-        "{" => "}"
-    ],
-    line_comment: ["//"],
-    block_comment: ["/*" => "*/"],
-    char: ["'"],
-    string: ["\""],
+    extends: C,
+    without: [marker, middle, continuation],
+    generic: ["<" => ">"],
     block_string: ["\"\"\"" => "\"\"\""]
 });
@@ -10,5 +10,6 @@ define_matcher!(Kotlin {
     line_comment: ["//"],
     block_comment: ["/*" => "*/"],
     string: ["\""],
+    char: ["'"],
     block_string: ["\"\"\"" => "\"\"\""]
 });
@@ -9,5 +9,7 @@ define_matcher!(Lean {
     ],
     line_comment: ["--"],
     block_comment: ["/-" => "-/"],
-    string: ["\""]
+    string: ["\""],
+    // Not a char literal in `x'`
+    char: ["'" @ word_start]
 });
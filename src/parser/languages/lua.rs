@@ -10,5 +10,7 @@ define_matcher!(Lua {
     line_comment: ["--"],
     block_comment: ["--[[" => "--]]"],
     string: ["\"", "'"],
-    block_string: ["[[" => "]]"]
+    block_string: ["[[" => "]]"],
+    escape: ["[[" => none],
+    region: ["#region" => "#endregion", "{{{" => "}}}"]
 });
@@ -2,13 +2,16 @@ mod c;
 mod clojure;
 mod cpp;
 mod csharp;
+mod cuda;
 mod dart;
 mod elixir;
 mod erlang;
 mod fsharp;
+mod glsl;
 mod go;
 mod haskell;
 mod haxe;
+mod hlsl;
 mod java;
 mod javascript;
 mod json;
@@ -17,6 +20,7 @@ mod latex;
 mod lean;
 mod lua;
 mod objc;
+mod objcpp;
 mod ocaml;
 mod perl;
 mod php;
@@ -26,22 +30,29 @@ mod ruby;
 mod rust;
 mod scala;
 mod shell;
+mod sql;
 mod swift;
 mod toml;
+mod tsx;
+mod typescript;
 mod typst;
+mod yaml;
 mod zig;
 
 pub use c::C;
 pub use clojure::Clojure;
 pub use cpp::Cpp;
 pub use csharp::CSharp;
+pub use cuda::Cuda;
 pub use dart::Dart;
 pub use elixir::Elixir;
 pub use erlang::Erlang;
 pub use fsharp::FSharp;
+pub use glsl::Glsl;
 pub use go::Go;
 pub use haskell::Haskell;
 pub use haxe::Haxe;
+pub use hlsl::Hlsl;
 pub use java::Java;
 pub use javascript::JavaScript;
 pub use json::Json;
@@ -50,6 +61,7 @@ pub use latex::Latex;
 pub use lean::Lean;
 pub use lua::Lua;
 pub use objc::ObjC;
+pub use objcpp::ObjCpp;
 pub use ocaml::OCaml;
 pub use perl::Perl;
 pub use php::Php;
@@ -59,7 +71,11 @@ pub use ruby::Ruby;
 pub use rust::Rust;
 pub use scala::Scala;
 pub use shell::Shell;
+pub use sql::Sql;
 pub use swift::Swift;
 pub use toml::Toml;
+pub use tsx::Tsx;
+pub use typescript::TypeScript;
 pub use typst::Typst;
+pub use yaml::Yaml;
 pub use zig::Zig;
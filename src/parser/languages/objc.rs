@@ -2,12 +2,6 @@ use crate::parser::*;
 use matcher_macros::define_matcher;
 
 define_matcher!(ObjC {
-    delimiters: [
-        "(" => ")",
-        "[" => "]",
-        "{" => "}"
-    ],
-    line_comment: ["//"],
-    block_comment: ["/*" => "*/"],
-    string: ["\""]
+    extends: C,
+    without: [char]
 });
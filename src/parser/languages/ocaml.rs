@@ -5,8 +5,11 @@ define_matcher!(OCaml {
     delimiters: [
         "(" => ")",
         "[" => "]",
-        "{" => "}"
+        "{" => "}",
+        "[|" => "|]"
     ],
     block_comment: ["(*" => "*)"],
-    string: ["\""]
+    string: ["\""],
+    // Not a char literal in `x'`
+    char: ["'" @ word_start]
 });
@@ -8,5 +8,22 @@ define_matcher!(Perl {
         "{" => "}"
     ],
     line_comment: ["#"],
+    // POD
+    block_comment: [
+        "=pod" @ line_start => "=cut" @ line_start,
+        "=head1" @ line_start => "=cut" @ line_start,
+        "=begin" @ line_start => "=cut" @ line_start
+    ],
     string: ["\"", "'"],
+    regex: ["/" => "/"],
+    quote_like: [
+        "q" => 1,
+        "qq" => 1,
+        "qw" => 1,
+        "qr" => 1,
+        "m" => 1,
+        "s" => 2,
+        "tr" => 2,
+        "y" => 2
+    ],
 });
@@ -10,5 +10,11 @@ define_matcher!(Python {
     line_comment: ["#"],
     block_comment: [],
     string: ["\"", "'"],
-    block_string: ["\"\"\"" => "\"\"\"", "'''" => "'''"]
+    continuation: [string],
+    block_string: ["\"\"\"" => "\"\"\"", "'''" => "'''"],
+    region: [
+        "#region" => "#endregion",
+        "# region" => "# endregion",
+        "{{{" => "}}}"
+    ]
 });
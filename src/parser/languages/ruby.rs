@@ -7,7 +7,25 @@ define_matcher!(Ruby {
         "[" => "]",
         "{" => "}"
     ],
+    // Block parameters, otherwise the `|` operator
+    symmetric: ["|" => line],
     line_comment: ["#"],
-    block_comment: ["=begin" => "end"],
-    string: ["\"", "'"]
+    block_comment: ["=begin" @ line_start => "=end" @ line_start],
+    string: ["\"", "'"],
+    regex: [
+        "/" => "/",
+        "%r{" => "}",
+        "%r(" => ")",
+        "%r[" => "]",
+        "%r<" => ">",
+        "%r|" => "|"
+    ],
+    quote_like: [
+        "%w" => 1,
+        "%W" => 1,
+        "%i" => 1,
+        "%I" => 1,
+        "%q" => 1,
+        "%Q" => 1
+    ]
 });
@@ -7,6 +7,10 @@ define_matcher!(Rust {
         "[" => "]",
         "{" => "}"
     ],
+    // Closure parameters, otherwise the `|` operator
+    symmetric: ["|" => line],
+    // Generic arguments, otherwise comparisons and shifts
+    generic: ["<" => ">"],
     line_comment: ["//"],
     block_comment: ["/*" => "*/"],
     char: ["'"],
@@ -15,5 +19,6 @@ define_matcher!(Rust {
         "r#\"" => "\"#",
         "r##\"" => "\"##",
         "r###\"" => "\"###"
-    ]
+    ],
+    escape: ["r#\"" => none, "r##\"" => none, "r###\"" => none]
 });
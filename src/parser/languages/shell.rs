@@ -7,6 +7,8 @@ define_matcher!(Shell {
         "[" => "]",
         "{" => "}"
     ],
-    line_comment: ["#"],
-    string: ["\"", "'"]
+    // Not a comment in `$#` or `${#var}`
+    line_comment: ["#" @ whitespace],
+    string: ["\"", "'"],
+    continuation: [string]
 });
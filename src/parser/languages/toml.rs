@@ -9,5 +9,6 @@ define_matcher!(Toml {
     ],
     line_comment: ["#"],
     string: ["\"", "'"],
-    block_string: ["\"\"\"" => "\"\"\""]
+    block_string: ["\"\"\"" => "\"\"\"", "'''" => "'''"],
+    escape: ["'" => none, "'''" => none]
 });
@@ -0,0 +1,8 @@
+use crate::parser::*;
+use matcher_macros::define_matcher;
+
+define_matcher!(Tsx {
+    extends: TypeScript,
+    // Conflicts with JSX tags, i.e. `</div>`
+    without: [generic]
+});
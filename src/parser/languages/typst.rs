@@ -7,6 +7,9 @@ define_matcher!(Typst {
         "[" => "]",
         "{" => "}"
     ],
+    // Math and strong emphasis, where `*` is only closed on the same line since it's also
+    // multiplication in code
+    symmetric: ["$", "*" => line],
     line_comment: ["//"],
     block_comment: ["/*" => "*/"],
     string: ["\"", "'"]
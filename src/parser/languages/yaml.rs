@@ -0,0 +1,13 @@
+use crate::parser::*;
+use matcher_macros::define_matcher;
+
+define_matcher!(Yaml {
+    delimiters: [
+        "[" => "]",
+        "{" => "}"
+    ],
+    // Not a comment in `key: a#b`
+    line_comment: ["#" @ whitespace],
+    string: ["\"", "'"],
+    escape: ["'" => doubled]
+});
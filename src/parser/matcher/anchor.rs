@@ -0,0 +1,46 @@
+/// Constraint on the surrounding text of a pattern, checked in addition to the pattern itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "spec",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Anchor {
+    /// At the first column of the line, i.e. `=begin` in Ruby
+    LineStart,
+    /// Only preceded by whitespace on the line, i.e. `#if` in C
+    Indent,
+    /// At the start of the line or preceded by whitespace, i.e. `#` in YAML and shell
+    Whitespace,
+    /// Not preceded nor followed by a word character, i.e. keywords like `begin`
+    Word,
+    /// Not preceded by a word character, i.e. `'` in Haskell where `x'` is an identifier
+    WordStart,
+    /// Not inside of a number, i.e. `'` in C where `1'000` and `0xF'FF` use digit separators
+    OutsideNumber,
+}
+
+impl Anchor {
+    /// Whether the anchor holds for the pattern of `len` bytes at `col` in the line
+    pub fn matches(&self, line: &[u8], col: usize, len: usize) -> bool {
+        let is_word = |byte: &u8| byte.is_ascii_alphanumeric() || *byte == b'_';
+        let before = col.checked_sub(1).and_then(|col| line.get(col));
+        match self {
+            Anchor::LineStart => col == 0,
+            Anchor::Indent => line[..col].iter().all(|byte| byte.is_ascii_whitespace()),
+            Anchor::Whitespace => before.is_none_or(|byte| byte.is_ascii_whitespace()),
+            Anchor::Word => {
+                !before.is_some_and(is_word) && !line.get(col + len).is_some_and(is_word)
+            }
+            Anchor::WordStart => !before.is_some_and(is_word),
+            Anchor::OutsideNumber => {
+                // Numbers start with a digit, and may hold letters and separators afterwards
+                let start = line[..col]
+                    .iter()
+                    .rposition(|byte| !is_word(byte) && *byte != b'\'')
+                    .map_or(0, |idx| idx + 1);
+                !line.get(start).is_some_and(u8::is_ascii_digit)
+            }
+        }
+    }
+}
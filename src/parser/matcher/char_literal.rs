@@ -0,0 +1,71 @@
+use itertools::MultiPeek;
+
+use crate::parser::CharPos;
+
+/// Longest escape sequence accepted in a char literal, i.e. `\u{10FFFF}`
+const MAX_ESCAPE_LEN: usize = 10;
+
+/// Length in bytes of the char literal starting with the delimiter at `col`, including both
+/// delimiters. The literal holds a single character, i.e. `'é'`, or an escape sequence, i.e.
+/// `'\''`, `'\x7f'` and `'\u{1F600}'`
+///
+/// Returns `None` when the delimiter doesn't start a char literal, i.e. Rust lifetimes (`'a`)
+/// and C++ digit separators (`1'000'000`)
+pub fn char_literal_len(line: &[u8], col: usize, delim: u8) -> Option<usize> {
+    let body = line.get(col + 1..)?;
+    let body_len = match *body.first()? {
+        b'\\' => {
+            // Escaped character, followed by the rest of the escape sequence
+            body.get(1)?;
+            2 + body[2..]
+                .iter()
+                .take(MAX_ESCAPE_LEN)
+                .take_while(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'{' | b'}'))
+                .count()
+        }
+        byte if byte == delim => return None,
+        // Single UTF-8 character
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return None,
+    };
+    (body.get(body_len) == Some(&delim)).then_some(body_len + 2)
+}
+
+/// Skips the tokens on the current line before `col`, i.e. the tokens inside of a char literal
+pub fn skip_to_col<I>(tokens: &mut MultiPeek<I>, col: usize)
+where
+    I: Iterator<Item = CharPos>,
+{
+    tokens.reset_peek();
+    while tokens
+        .peek()
+        .is_some_and(|token| token.byte != b'\n' && token.col < col)
+    {
+        tokens.next();
+    }
+    tokens.reset_peek();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_literal_len() {
+        let len = |line: &str| char_literal_len(line.as_bytes(), 0, b'\'');
+        assert_eq!(len("'a'"), Some(3));
+        assert_eq!(len("'é'"), Some(4));
+        assert_eq!(len(r"'\''"), Some(4));
+        assert_eq!(len(r"'\\'"), Some(4));
+        assert_eq!(len(r"'\x7f'"), Some(6));
+        assert_eq!(len(r"'\u{1F600}'"), Some(11));
+        assert_eq!(len("'a"), None);
+        assert_eq!(len("'a>"), None);
+        assert_eq!(len("''"), None);
+        assert_eq!(len("'000'"), None);
+        assert_eq!(len(r"'\"), None);
+    }
+}
@@ -0,0 +1,16 @@
+/// How the closing delimiter of a string or block string may be escaped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "spec",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Escape {
+    /// Can't be escaped, i.e. Rust raw strings and Go backtick strings
+    None,
+    /// Preceded by a backslash
+    #[default]
+    Backslash,
+    /// Repeated twice, i.e. `''` in SQL strings and `""` in C# verbatim strings
+    Doubled,
+}
@@ -0,0 +1,75 @@
+/// Keywords after which an opening angle bracket starts generic parameters, even when separated
+/// by whitespace, i.e. `template <typename T>`
+const GENERIC_KEYWORDS: &[&str] = &["template"];
+
+fn is_word(byte: &u8) -> bool {
+    byte.is_ascii_alphanumeric() || *byte == b'_' || *byte == b'$'
+}
+
+/// Whether the angle bracket at `col` opens generic arguments, i.e. `Vec<T>`, `::<T>` and
+/// `template <T>`, rather than `a < b`, `1<2`, `<<` or `<=`
+pub fn opens_generic(line: &[u8], col: usize) -> bool {
+    if matches!(line.get(col + 1), Some(b'<' | b'=')) {
+        return false;
+    }
+
+    let before = &line[..col];
+    if before.ends_with(b"::") {
+        return true;
+    }
+    let end = before.iter().rposition(|byte| !byte.is_ascii_whitespace());
+    let Some(end) = end else {
+        return false;
+    };
+    if !is_word(&before[end]) {
+        return false;
+    }
+    let start = before[..end]
+        .iter()
+        .rposition(|byte| !is_word(byte))
+        .map_or(0, |idx| idx + 1);
+    let word = &before[start..=end];
+    if word[0].is_ascii_digit() {
+        return false;
+    }
+    // Only keywords may be separated from the bracket by whitespace
+    end + 1 == col
+        || GENERIC_KEYWORDS
+            .iter()
+            .any(|keyword| keyword.as_bytes() == word)
+}
+
+/// Whether the angle bracket at `col` may close generic arguments, rather than being part of
+/// `->`, `=>` or `>=`, or a comparison surrounded by whitespace
+pub fn closes_generic(line: &[u8], col: usize) -> bool {
+    let prev = col.checked_sub(1).map(|idx| line[idx]);
+    !matches!(prev, Some(b'-' | b'=') | None)
+        && !prev.is_some_and(|byte| byte.is_ascii_whitespace())
+        && line.get(col + 1) != Some(&b'=')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generic() {
+        let opens = |line: &str| opens_generic(line.as_bytes(), line.find('<').unwrap());
+        assert!(opens("Vec<T>"));
+        assert!(opens("collect::<Vec<_>>()"));
+        assert!(opens("template <typename T>"));
+        assert!(!opens("a < b"));
+        assert!(!opens("1<2"));
+        assert!(!opens("a << b"));
+        assert!(!opens("a<=b"));
+        assert!(!opens("<T>"));
+
+        let closes = |line: &str| closes_generic(line.as_bytes(), line.rfind('>').unwrap());
+        assert!(closes("Vec<T>"));
+        assert!(closes("Vec<Vec<T>>"));
+        assert!(!closes("fn a() -> T"));
+        assert!(!closes("a => b"));
+        assert!(!closes("a<b >= c"));
+        assert!(!closes("a<b > c"));
+    }
+}
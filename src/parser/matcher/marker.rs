@@ -0,0 +1,37 @@
+use crate::parser::{Kind, Match, Token};
+
+/// Whether the marker is at `col` in the line and isn't followed by a word character, so that
+/// `#if` doesn't match `#ifdef`. Markers are lexed from the line since only their first byte is
+/// a token, i.e. `#` in `#ifdef`
+pub fn marker_at(line: &[u8], col: usize, marker: &str) -> bool {
+    let Some(rest) = line.get(col..) else {
+        return false;
+    };
+    rest.starts_with(marker.as_bytes())
+        && !rest
+            .get(marker.len())
+            .is_some_and(|byte| byte.is_ascii_alphanumeric() || *byte == b'_')
+}
+
+/// Closing marker of git conflicts, identifying the conflict markers among the markers
+pub const CONFLICT_CLOSE: &str = ">>>>>>>";
+
+/// Git conflict marker at the start of the line, i.e. `<<<<<<< HEAD`, detected for every
+/// language. `|||||||` (followed by the base with diff3) and `=======` separate the sides
+pub fn conflict_marker(line: &[u8]) -> Option<Match> {
+    let (kind, marker) = [
+        (Kind::Opening, "<<<<<<<"),
+        (Kind::Middle, "|||||||"),
+        (Kind::Middle, "======="),
+        (Kind::Closing, CONFLICT_CLOSE),
+    ]
+    .into_iter()
+    .find(|(_, marker)| line.starts_with(marker.as_bytes()))?;
+    if line
+        .get(marker.len())
+        .is_some_and(|byte| !byte.is_ascii_whitespace())
+    {
+        return None;
+    }
+    Some(Match::new(kind, Token::Marker(marker, CONFLICT_CLOSE), 0))
+}
@@ -1,13 +1,39 @@
 use itertools::MultiPeek;
-use mlua::IntoLua;
 
+mod anchor;
+mod char_literal;
+mod escape;
+mod generic;
+mod marker;
+mod quote_like;
+mod regex_literal;
+mod region;
+mod symmetric;
 mod token;
 mod token_type;
 
+pub use anchor::*;
+pub use char_literal::*;
+pub use escape::*;
+pub use generic::*;
+pub use marker::*;
+pub use quote_like::*;
+pub use regex_literal::*;
+pub use region::*;
+pub use symmetric::*;
 pub use token::*;
 pub use token_type::*;
 
-use crate::parser::{CharPos, State};
+use crate::parser::{CharPos, LanguageSpec, State};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatcherMetadata {
+    /// Name of the matcher, i.e. `Cpp`
+    pub name: &'static str,
+    /// Language extended with `extends`, i.e. `C` for `Cpp`. For runtime-defined languages,
+    /// this is the filetype of the base language
+    pub extends: Option<&'static str>,
+}
 
 pub trait Matcher {
     const TOKENS: &[u8];
@@ -16,14 +42,33 @@ pub trait Matcher {
         Self::TOKENS
     }
 
+    /// Description of the language in the same format as the runtime-defined languages, with
+    /// the sections of the base languages merged in
+    fn describe(&self) -> LanguageSpec;
+
+    fn metadata(&self) -> MatcherMetadata;
+
+    /// Whether the state carries over to the next line when the line ends with a backslash,
+    /// i.e. strings and line comments in C. Otherwise, strings and line comments end at the end
+    /// of the line
+    fn continues_line(&self, state: State) -> bool;
+
+    /// Delimiters limited to a single line by their closing delimiter on the stack, i.e.
+    /// symmetric delimiters with [`Limit::Line`] and generic angle brackets
+    fn same_line_delimiters(&self) -> &'static [&'static str];
+
+    /// The stack holds the closing delimiter of each open delimiter pair, and the line holds
+    /// the text of the line the token is on, for checking anchors
+    #[allow(clippy::too_many_arguments)]
     fn call<I>(
         &mut self,
         matches: &mut Vec<Match>,
-        stack: &mut Vec<u8>,
+        stack: &mut Vec<&'static str>,
         tokens: &mut MultiPeek<I>,
         state: State,
         token: CharPos,
         escaped: bool,
+        line: &[u8],
     ) -> State
     where
         I: Iterator<Item = CharPos>;
@@ -35,6 +80,9 @@ pub struct Match {
     pub token: Token,
     pub col: usize,
     pub stack_height: Option<usize>,
+    /// Height among the delimiters of the same type, i.e. only counting the open parentheses
+    /// for `(`. Calculated by the buffer, along with the heights of markers
+    pub type_stack_height: Option<usize>,
 }
 
 impl Match {
@@ -44,6 +92,7 @@ impl Match {
             token,
             col,
             stack_height: None,
+            type_stack_height: None,
         }
     }
 
@@ -53,6 +102,7 @@ impl Match {
             token,
             col,
             stack_height: Some(stack_height),
+            type_stack_height: None,
         }
     }
 
@@ -63,6 +113,7 @@ impl Match {
             line,
             col: self.col,
             stack_height: self.stack_height,
+            type_stack_height: self.type_stack_height,
         }
     }
 
@@ -72,18 +123,13 @@ impl Match {
             token: Token::LineComment(text),
             col,
             stack_height: None,
+            type_stack_height: None,
         }
     }
 
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
-        match self.kind {
-            Kind::Opening | Kind::NonPair => self.token.opening().len(),
-            Kind::Closing => self
-                .token
-                .closing()
-                .unwrap_or_else(|| self.token.opening())
-                .len(),
-        }
+        self.token.text(self.kind).len()
     }
 }
 
@@ -105,6 +151,7 @@ impl Match {
             token,
             col,
             stack_height,
+            type_stack_height: None,
         }
     }
 
@@ -119,25 +166,11 @@ impl Match {
             token,
             col,
             stack_height: None,
+            type_stack_height: None,
         }
     }
 }
 
-impl IntoLua for Match {
-    fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
-        let table = lua.create_table()?;
-
-        table.set(0, self.token.opening())?;
-        if let Some(closing) = self.token.closing() {
-            table.set(1, closing)?;
-        }
-        table.set("col", self.col)?;
-        table.set("stack_height", self.stack_height)?;
-
-        (&table).into_lua(lua)
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct MatchWithLine {
     pub kind: Kind,
@@ -145,20 +178,12 @@ pub struct MatchWithLine {
     pub line: usize,
     pub col: usize,
     pub stack_height: Option<usize>,
+    pub type_stack_height: Option<usize>,
 }
 
-impl IntoLua for MatchWithLine {
-    fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
-        let table = lua.create_table()?;
-
-        table.set(1, self.token.opening())?;
-        if let Some(closing) = self.token.closing() {
-            table.set(2, closing)?;
-        }
-        table.set("line", self.line)?;
-        table.set("col", self.col)?;
-        table.set("stack_height", self.stack_height)?;
-
-        (&table).into_lua(lua)
+impl MatchWithLine {
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.token.text(self.kind).len()
     }
 }
@@ -0,0 +1,40 @@
+/// Delimiters that may follow the prefix of a quote-like operator, i.e. `q{` or `%w[`. When the
+/// opening and closing delimiters differ, nested pairs must be closed before the quote
+pub const QUOTE_LIKE_DELIMITERS: &[(&str, &str)] = &[
+    ("(", ")"),
+    ("[", "]"),
+    ("{", "}"),
+    ("<", ">"),
+    ("/", "/"),
+    ("|", "|"),
+    ("!", "!"),
+    ("#", "#"),
+    ("\"", "\""),
+    ("'", "'"),
+    ("\"\"\"", "\"\"\""),
+    ("'''", "'''"),
+];
+
+/// Whether the quote-like prefix at `col` starts an operator, rather than ending an identifier
+/// (`has/2`), a variable (`$s/2`) or a method call (`$obj->s(1)`)
+pub fn quote_like_start(line: &[u8], col: usize) -> bool {
+    let before = &line[..col];
+    match before.last() {
+        None => true,
+        Some(byte) if byte.is_ascii_alphanumeric() => false,
+        Some(b'_' | b'$' | b'@' | b'%' | b'&') => false,
+        Some(_) => !before.ends_with(b"->") && !before.ends_with(b"::"),
+    }
+}
+
+/// Whether the second part of a quote-like operator with bracketed delimiters may follow the
+/// first part ending before `col`, i.e. `s{a}{b}` or `s{a} (b)`. The second part may also start
+/// on the next line
+pub fn quote_like_second_part(line: &[u8], col: usize) -> bool {
+    let rest = line.get(col..).unwrap_or_default().trim_ascii_start();
+    rest.first().is_none_or(|&byte| {
+        QUOTE_LIKE_DELIMITERS
+            .iter()
+            .any(|(open, close)| open != close && open.as_bytes() == [byte])
+    })
+}
@@ -0,0 +1,124 @@
+/// Keywords after which `/` starts a regex rather than a division
+const REGEX_KEYWORDS: &[&str] = &[
+    "and",
+    "await",
+    "case",
+    "delete",
+    "do",
+    "else",
+    "elsif",
+    "grep",
+    "if",
+    "in",
+    "instanceof",
+    "join",
+    "map",
+    "new",
+    "not",
+    "of",
+    "or",
+    "return",
+    "split",
+    "throw",
+    "typeof",
+    "unless",
+    "until",
+    "void",
+    "when",
+    "while",
+    "yield",
+];
+
+/// Whether an operand is expected at `col`, based on the previous significant token on the
+/// line, so that `/` starts a regex in `x = /a/` but is a division in `a / b / c`
+fn expects_operand(line: &[u8], col: usize) -> bool {
+    let is_word = |byte: &u8| byte.is_ascii_alphanumeric() || *byte == b'_' || *byte == b'$';
+    let before = &line[..col];
+    let Some(end) = before.iter().rposition(|byte| !byte.is_ascii_whitespace()) else {
+        return true;
+    };
+    match before[end] {
+        b')' | b']' | b'}' | b'"' | b'\'' | b'`' => false,
+        // Closing tags in JSX, i.e. `</div>`
+        b'<' => false,
+        byte if is_word(&byte) => {
+            let start = before[..end]
+                .iter()
+                .rposition(|byte| !is_word(byte))
+                .map_or(0, |idx| idx + 1);
+            let word = &before[start..=end];
+            REGEX_KEYWORDS
+                .iter()
+                .any(|keyword| keyword.as_bytes() == word)
+        }
+        _ => true,
+    }
+}
+
+/// Length in bytes of the regex literal starting with `open` at `col`, up to and including
+/// `close`. Escaped characters and character classes (`[/]`) don't close the regex, and when
+/// `open` ends with a bracket (`%r{`), nested brackets must be balanced first
+///
+/// Single character openers (`/`) are also operators, so they only start a regex where an
+/// operand is expected. Regex literals must be closed on the same line
+pub fn regex_literal_len(line: &[u8], col: usize, open: &str, close: &str) -> Option<usize> {
+    if open.len() == 1 && !expects_operand(line, col) {
+        return None;
+    }
+    let nested = open.bytes().last().filter(|&byte| {
+        matches!(
+            (byte, close.as_bytes()),
+            (b'(', b")") | (b'[', b"]") | (b'{', b"}") | (b'<', b">")
+        )
+    });
+
+    let start = col + open.len();
+    let mut idx = start;
+    let mut depth = 0;
+    let mut in_class = false;
+    while idx < line.len() {
+        let byte = line[idx];
+        if byte == b'\\' {
+            idx += 2;
+            continue;
+        }
+        if in_class {
+            in_class = byte != b']';
+        } else if line[idx..].starts_with(close.as_bytes()) && depth == 0 {
+            // Empty regexes are comments or operators, i.e. `//`
+            return (idx > start).then_some(idx + close.len() - col);
+        } else if Some(byte) == nested {
+            depth += 1;
+        } else if nested.is_some() && line[idx..].starts_with(close.as_bytes()) {
+            depth -= 1;
+        } else if byte == b'[' {
+            in_class = true;
+        }
+        idx += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_literal_len() {
+        let len = |line: &str, col: usize, open: &str, close: &str| {
+            regex_literal_len(line.as_bytes(), col, open, close)
+        };
+        assert_eq!(len("x = /[(]/g", 4, "/", "/"), Some(5));
+        assert_eq!(len(r#"/\/"/"#, 0, "/", "/"), Some(5));
+        assert_eq!(len("/[/]/", 0, "/", "/"), Some(5));
+        assert_eq!(len("return /a/", 7, "/", "/"), Some(3));
+        // Divisions
+        assert_eq!(len("a / b / c", 2, "/", "/"), None);
+        assert_eq!(len("f(x) / 2 / 3", 5, "/", "/"), None);
+        assert_eq!(len("<a>x</a><b>y</b>", 4, "/", "/"), None);
+        // Nested brackets
+        assert_eq!(len("%r{a{2}}x}", 0, "%r{", "}"), Some(8));
+        assert_eq!(len("foo %r{a}", 4, "%r{", "}"), Some(5));
+        assert_eq!(len("%r{a", 0, "%r{", "}"), None);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::parser::{Kind, Match, Token};
+
+/// Whether the region marker is at `pos` in the line, not preceded by a word character and not
+/// followed by a letter, so that `{{{1` (with a fold level) matches but `#regional` doesn't
+fn region_at(line: &[u8], pos: usize, marker: &str) -> bool {
+    let is_word = |byte: &u8| byte.is_ascii_alphanumeric() || *byte == b'_';
+    line[pos..].starts_with(marker.as_bytes())
+        && !pos.checked_sub(1).is_some_and(|prev| is_word(&line[prev]))
+        && !line
+            .get(pos + marker.len())
+            .is_some_and(|byte| byte.is_ascii_alphabetic() || *byte == b'_')
+}
+
+/// First region marker in the line comment starting at `col`, including the comment itself,
+/// i.e. `#region` in `// #region` and `#region` in Python. The regions are lexed from the line
+/// since everything in a line comment is skipped
+pub fn region_in_comment(
+    line: &[u8],
+    col: usize,
+    regions: &[(&'static str, &'static str)],
+) -> Option<Match> {
+    (col..line.len()).find_map(|pos| {
+        regions.iter().find_map(|&(open, close)| {
+            let kind = if region_at(line, pos, open) {
+                Kind::Opening
+            } else if region_at(line, pos, close) {
+                Kind::Closing
+            } else {
+                return None;
+            };
+            Some(Match::new(kind, Token::Region(open, close), pos))
+        })
+    })
+}
@@ -0,0 +1,103 @@
+use crate::parser::{Kind, Match, Token};
+
+/// How far a symmetric delimiter may extend before its closing delimiter, i.e. `|` in Rust
+/// closures must be closed on the same line, otherwise it's the `|` operator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "spec",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Limit {
+    /// May span lines, i.e. `$` in Typst
+    #[default]
+    None,
+    /// Dropped when left open at the end of the line, or before closing an outer delimiter
+    Line,
+}
+
+/// Drops the line-limited delimiters left open above `close` on the stack, so that `)` closes
+/// `(` in `(a | b)` and `(i<n)`. Does nothing when `close` isn't on the stack, or when a regular
+/// delimiter is open above it
+pub fn unwind_symmetric(
+    matches: &mut Vec<Match>,
+    stack: &mut Vec<&'static str>,
+    close: &str,
+    same_line: &[&str],
+) {
+    let Some(idx) = stack.iter().rposition(|delim| *delim == close) else {
+        return;
+    };
+    if stack[idx + 1..]
+        .iter()
+        .all(|delim| same_line.contains(delim))
+    {
+        for idx in (idx + 1..stack.len()).rev() {
+            drop_symmetric(matches, stack, idx);
+        }
+    }
+}
+
+/// Drops the line-limited delimiters left open at the end of the line
+pub fn unwind_symmetric_line(
+    matches: &mut Vec<Match>,
+    stack: &mut Vec<&'static str>,
+    same_line: &[&str],
+) {
+    for idx in (0..stack.len()).rev() {
+        if same_line.contains(&stack[idx]) {
+            drop_symmetric(matches, stack, idx);
+        }
+    }
+}
+
+/// Removes the delimiter at `idx` on the stack along with its opening match, lowering the
+/// delimiters opened after it
+fn drop_symmetric(matches: &mut Vec<Match>, stack: &mut Vec<&'static str>, idx: usize) {
+    let close = stack.remove(idx);
+    let Some(opening) = matches.iter().rposition(|match_| {
+        match_.kind == Kind::Opening
+            && matches!(match_.token, Token::Delimiter(_, delim) if delim == close)
+            && match_.stack_height == Some(idx)
+    }) else {
+        return;
+    };
+    matches.remove(opening);
+    for match_ in &mut matches[opening..] {
+        if let (Token::Delimiter(..), Some(height)) = (&match_.token, &mut match_.stack_height) {
+            if *height > idx {
+                *height -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unwind_symmetric() {
+        let bar = Token::Delimiter("|", "|");
+        let paren = Token::Delimiter("(", ")");
+        // `(a | (b)`
+        let mut matches = vec![
+            Match::new_with_stack(Kind::Opening, paren.clone(), 0, 0),
+            Match::new_with_stack(Kind::Opening, bar, 3, 1),
+            Match::new_with_stack(Kind::Opening, paren.clone(), 5, 2),
+            Match::new_with_stack(Kind::Closing, paren.clone(), 7, 2),
+        ];
+        let mut stack = vec![")", "|"];
+
+        unwind_symmetric(&mut matches, &mut stack, ")", &["|"]);
+        assert_eq!(stack, vec![")"]);
+        assert_eq!(
+            matches,
+            vec![
+                Match::new_with_stack(Kind::Opening, paren.clone(), 0, 0),
+                Match::new_with_stack(Kind::Opening, paren.clone(), 5, 1),
+                Match::new_with_stack(Kind::Closing, paren, 7, 1),
+            ]
+        );
+    }
+}
@@ -5,6 +5,8 @@ pub enum Kind {
     Opening,
     Closing,
     NonPair,
+    /// Separates the branches of a marker pair, i.e. `#else` between `#if` and `#endif`
+    Middle,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -16,26 +18,54 @@ pub enum Token {
 
     LineComment(&'static str),
     BlockComment(&'static str, &'static str),
+
+    /// Line-based pair with optional middles, i.e. `#ifdef`, `#else` and `#endif`. Holds the
+    /// text of the marker and the closing marker of its pair, since the opening and middle
+    /// markers may vary for the same closing marker
+    Marker(&'static str, &'static str),
+
+    /// Region marker inside of a line comment, i.e. `#region` in `// #region` and `{{{` in
+    /// Vim fold markers. Holds the opening and closing marker of the region
+    Region(&'static str, &'static str),
+
+    /// Delimiter inside of a string or comment, for languages matching delimiters in the
+    /// sections listed in `embedded`. Paired on its own stack, without affecting the heights of
+    /// the other delimiters
+    Embedded(&'static str, &'static str),
 }
 
 impl Token {
     pub fn opening(&self) -> &'static str {
         match self {
-            Token::Delimiter(open, _) => *open,
-            Token::String(open) => *open,
-            Token::BlockString(open, _) => *open,
-            Token::LineComment(open) => *open,
-            Token::BlockComment(open, _) => *open,
+            Token::Delimiter(open, _) => open,
+            Token::String(open) => open,
+            Token::BlockString(open, _) => open,
+            Token::LineComment(open) => open,
+            Token::BlockComment(open, _) => open,
+            Token::Marker(text, _) => text,
+            Token::Region(open, _) => open,
+            Token::Embedded(open, _) => open,
         }
     }
 
     pub fn closing(&self) -> Option<&'static str> {
         match self {
-            Token::Delimiter(_, close) => Some(*close),
+            Token::Delimiter(_, close) => Some(close),
             Token::String(_) => None,
-            Token::BlockString(_, close) => Some(*close),
+            Token::BlockString(_, close) => Some(close),
             Token::LineComment(_) => None,
-            Token::BlockComment(_, close) => Some(*close),
+            Token::BlockComment(_, close) => Some(close),
+            Token::Marker(_, close) => Some(close),
+            Token::Region(_, close) => Some(close),
+            Token::Embedded(_, close) => Some(close),
+        }
+    }
+
+    /// Text of a match of the given kind, i.e. the closing delimiter for a closing match
+    pub fn text(&self, kind: Kind) -> &'static str {
+        match kind {
+            Kind::Opening | Kind::NonPair | Kind::Middle => self.opening(),
+            Kind::Closing => self.closing().unwrap_or_else(|| self.opening()),
         }
     }
 }
@@ -8,19 +8,39 @@ pub enum TokenType {
     BlockString = 2,
     LineComment = 3,
     BlockComment = 4,
+    Marker = 5,
+    Region = 6,
+    Embedded = 7,
 }
 
 impl TokenType {
     pub fn matches(&self, token: &Token) -> bool {
         use TokenType::*;
-        match (self, token) {
+        matches!(
+            (self, token),
             (Delimiter, Token::Delimiter(_, _))
-            | (String, Token::String(_))
-            | (BlockString, Token::BlockString(_, _))
-            | (LineComment, Token::LineComment(_))
-            | (BlockComment, Token::BlockComment(_, _)) => true,
+                | (String, Token::String(_))
+                | (BlockString, Token::BlockString(_, _))
+                | (LineComment, Token::LineComment(_))
+                | (BlockComment, Token::BlockComment(_, _))
+                | (Marker, Token::Marker(_, _))
+                | (Region, Token::Region(_, _))
+                | (Embedded, Token::Embedded(_, _))
+        )
+    }
+}
 
-            _ => false,
+impl From<&Token> for TokenType {
+    fn from(token: &Token) -> Self {
+        match token {
+            Token::Delimiter(_, _) => TokenType::Delimiter,
+            Token::String(_) => TokenType::String,
+            Token::BlockString(_, _) => TokenType::BlockString,
+            Token::LineComment(_) => TokenType::LineComment,
+            Token::BlockComment(_, _) => TokenType::BlockComment,
+            Token::Marker(_, _) => TokenType::Marker,
+            Token::Region(_, _) => TokenType::Region,
+            Token::Embedded(_, _) => TokenType::Embedded,
         }
     }
 }
@@ -35,6 +55,9 @@ impl TryFrom<u8> for TokenType {
             2 => Ok(TokenType::BlockString),
             3 => Ok(TokenType::LineComment),
             4 => Ok(TokenType::BlockComment),
+            5 => Ok(TokenType::Marker),
+            6 => Ok(TokenType::Region),
+            7 => Ok(TokenType::Embedded),
             _ => Err(()),
         }
     }
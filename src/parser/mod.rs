@@ -1,54 +1,125 @@
 pub type SimdVec = std::simd::Simd<u8, 16>;
 
+pub mod dynamic;
+pub mod fallback;
+pub mod filetype;
 pub mod languages;
 pub mod matcher;
 pub mod parse;
 pub mod tokenize;
 
+pub use dynamic::{register_language, DynamicMatcher, LanguageSpec};
+pub use fallback::{fallback_matcher, fallback_spec};
 pub use itertools::MultiPeek;
-pub use matcher::{Kind, Match, MatchWithLine, Matcher, Token};
+pub use matcher::{
+    char_literal_len, closes_generic, conflict_marker, marker_at, opens_generic,
+    quote_like_second_part, quote_like_start, regex_literal_len, region_in_comment, skip_to_col,
+    unwind_symmetric, unwind_symmetric_line, Anchor, Escape, Kind, Limit, Match, MatchWithLine,
+    Matcher, MatcherMetadata, Token, TokenType, CONFLICT_CLOSE, QUOTE_LIKE_DELIMITERS,
+};
 pub use parse::{parse, State};
 pub use tokenize::{tokenize, CharPos};
 
+macro_rules! builtin_languages {
+    ($($filetype:literal => $matcher:ident),* $(,)?) => {
+        /// Filetypes of the languages defined with `define_matcher!`
+        pub const BUILTIN_FILETYPES: &[&str] = &[$($filetype),*];
+
+        fn parse_builtin(
+            filetype: &str,
+            lines: &[&str],
+            initial_state: State,
+        ) -> Option<(Vec<Vec<Match>>, Vec<State>)> {
+            match filetype {
+                $($filetype => Some(parse(lines, initial_state, languages::$matcher {})),)*
+                _ => None,
+            }
+        }
+
+        /// Spec of the built-in language, ignoring languages registered at runtime
+        pub fn builtin_spec(filetype: &str) -> Option<LanguageSpec> {
+            match filetype {
+                $($filetype => Some(languages::$matcher {}.describe()),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+builtin_languages! {
+    "c" => C,
+    "clojure" => Clojure,
+    "cpp" => Cpp,
+    "csharp" => CSharp,
+    "cuda" => Cuda,
+    "dart" => Dart,
+    "elixir" => Elixir,
+    "erlang" => Erlang,
+    "fsharp" => FSharp,
+    "glsl" => Glsl,
+    "go" => Go,
+    "haskell" => Haskell,
+    "haxe" => Haxe,
+    "hlsl" => Hlsl,
+    "java" => Java,
+    "javascript" => JavaScript,
+    "json" => Json,
+    "kotlin" => Kotlin,
+    "latex" => Latex,
+    "lean" => Lean,
+    "lua" => Lua,
+    "objc" => ObjC,
+    "objcpp" => ObjCpp,
+    "ocaml" => OCaml,
+    "perl" => Perl,
+    "php" => Php,
+    "python" => Python,
+    "r" => R,
+    "ruby" => Ruby,
+    "rust" => Rust,
+    "scala" => Scala,
+    "shell" => Shell,
+    "sql" => Sql,
+    "swift" => Swift,
+    "toml" => Toml,
+    "typescript" => TypeScript,
+    "typescriptreact" => Tsx,
+    "typst" => Typst,
+    "yaml" => Yaml,
+    "zig" => Zig,
+}
+
 pub fn parse_filetype(
     filetype: &str,
     lines: &[&str],
     initial_state: State,
 ) -> Option<(Vec<Vec<Match>>, Vec<State>)> {
-    match filetype {
-        "c" => Some(parse(lines, initial_state, languages::C {})),
-        "clojure" => Some(parse(lines, initial_state, languages::Clojure {})),
-        "cpp" => Some(parse(lines, initial_state, languages::Cpp {})),
-        "csharp" => Some(parse(lines, initial_state, languages::CSharp {})),
-        "dart" => Some(parse(lines, initial_state, languages::Dart {})),
-        "elixir" => Some(parse(lines, initial_state, languages::Elixir {})),
-        "erlang" => Some(parse(lines, initial_state, languages::Erlang {})),
-        "fsharp" => Some(parse(lines, initial_state, languages::FSharp {})),
-        "go" => Some(parse(lines, initial_state, languages::Go {})),
-        "haskell" => Some(parse(lines, initial_state, languages::Haskell {})),
-        "haxe" => Some(parse(lines, initial_state, languages::Haxe {})),
-        "java" => Some(parse(lines, initial_state, languages::Java {})),
-        "javascript" => Some(parse(lines, initial_state, languages::JavaScript {})),
-        "json" => Some(parse(lines, initial_state, languages::Json {})),
-        "kotlin" => Some(parse(lines, initial_state, languages::Kotlin {})),
-        "latex" => Some(parse(lines, initial_state, languages::Latex {})),
-        "lean" => Some(parse(lines, initial_state, languages::Lean {})),
-        "lua" => Some(parse(lines, initial_state, languages::Lua {})),
-        "objc" => Some(parse(lines, initial_state, languages::ObjC {})),
-        "ocaml" => Some(parse(lines, initial_state, languages::OCaml {})),
-        "perl" => Some(parse(lines, initial_state, languages::Perl {})),
-        "php" => Some(parse(lines, initial_state, languages::Php {})),
-        "python" => Some(parse(lines, initial_state, languages::Python {})),
-        "r" => Some(parse(lines, initial_state, languages::R {})),
-        "ruby" => Some(parse(lines, initial_state, languages::Ruby {})),
-        "rust" => Some(parse(lines, initial_state, languages::Rust {})),
-        "scala" => Some(parse(lines, initial_state, languages::Scala {})),
-        "shell" => Some(parse(lines, initial_state, languages::Shell {})),
-        "swift" => Some(parse(lines, initial_state, languages::Swift {})),
-        "toml" => Some(parse(lines, initial_state, languages::Toml {})),
-        "typst" => Some(parse(lines, initial_state, languages::Typst {})),
-        "zig" => Some(parse(lines, initial_state, languages::Zig {})),
-
-        _ => None,
+    let filetype = filetype::resolve(filetype)?;
+
+    // Languages registered at runtime take precedence over the built-in languages
+    if let Some(matcher) = dynamic::registered_language(filetype) {
+        return Some(parse(lines, initial_state, matcher));
     }
+    parse_builtin(filetype, lines, initial_state)
+}
+
+/// Spec of the language used for the filetype, including languages registered at runtime
+pub fn language_spec(filetype: &str) -> Option<LanguageSpec> {
+    let filetype = filetype::resolve(filetype)?;
+    dynamic::registered_language(filetype)
+        .map(|matcher| matcher.describe())
+        .or_else(|| builtin_spec(filetype))
+}
+
+/// Filetypes accepted by [`parse_filetype`], excluding dotted filetypes, sorted
+pub fn supported_filetypes() -> Vec<String> {
+    let mut filetypes = BUILTIN_FILETYPES
+        .iter()
+        .chain(filetype::ALIASES.iter().map(|(alias, _)| alias))
+        .map(|filetype| filetype.to_string())
+        .chain(dynamic::registered_filetypes())
+        .collect::<Vec<_>>();
+    filetypes.sort();
+    filetypes.dedup();
+    filetypes
 }
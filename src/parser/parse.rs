@@ -1,7 +1,7 @@
 use itertools::Itertools;
 
 use super::{
-    matcher::{Match, Matcher},
+    matcher::{conflict_marker, unwind_symmetric_line, Kind, Match, Matcher},
     tokenize::tokenize,
 };
 
@@ -9,14 +9,65 @@ use super::{
 pub enum State {
     Normal,
     InString(&'static str),
-    InBlockString(&'static str),
+    /// Opening text of the block string, and the depth of the nested pairs of bracketed
+    /// delimiters inside of quote-like operators, i.e. `1` after `q{a{`
+    InBlockString(&'static str, usize),
     InLineComment,
     InBlockComment(&'static str),
 }
 
+/// Git conflict being parsed, so that each side starts from the stack and state before the
+/// conflict
+struct Conflict {
+    stack: Vec<&'static str>,
+    state: State,
+    /// Stack and state at the end of the first side, continued after the conflict
+    first_side: Option<(Vec<&'static str>, State)>,
+}
+
+/// Handles the git conflict marker at the start of the line, for every language. Returns
+/// whether the line holds a marker, in which case the rest of the line is skipped
+fn parse_conflict(
+    line: &str,
+    line_matches: &mut Vec<Match>,
+    stack: &mut Vec<&'static str>,
+    state: &mut State,
+    conflicts: &mut Vec<Conflict>,
+) -> bool {
+    let Some(marker) = conflict_marker(line.as_bytes()) else {
+        return false;
+    };
+    match marker.kind {
+        Kind::Opening => conflicts.push(Conflict {
+            stack: stack.clone(),
+            state: *state,
+            first_side: None,
+        }),
+        Kind::Middle => {
+            if let Some(conflict) = conflicts.last_mut() {
+                conflict
+                    .first_side
+                    .get_or_insert_with(|| (stack.clone(), *state));
+                *stack = conflict.stack.clone();
+                *state = conflict.state;
+            }
+        }
+        _ => {
+            if let Some((first_stack, first_state)) =
+                conflicts.pop().and_then(|conflict| conflict.first_side)
+            {
+                *stack = first_stack;
+                *state = first_state;
+            }
+        }
+    }
+    line_matches.push(marker);
+    true
+}
+
 /// Given a matcher, runs the tokenizer on the lines and keeps track
 /// of the state and matches for each line
-pub fn parse<M: Matcher>(
+pub fn parse<M>(
     lines: &[&str],
     initial_state: State,
     mut matcher: M,
@@ -33,6 +84,18 @@ where
 
     let mut stack = vec![];
     let mut escaped_col: Option<usize> = None;
+    let mut line_number = 0;
+
+    let mut conflicts = vec![];
+    let mut conflict_line = lines.first().is_some_and(|line| {
+        parse_conflict(
+            line,
+            &mut line_matches,
+            &mut stack,
+            &mut state,
+            &mut conflicts,
+        )
+    });
 
     let text = lines.join("\n");
 
@@ -48,14 +111,34 @@ where
     while let Some(token) = tokens.next() {
         // New line
         if matches!(token.byte, b'\n') {
+            // The line ends with an unescaped backslash
+            let continued = escaped_col.is_some_and(|col| col + 1 == lines[line_number].len())
+                && matcher.continues_line(state);
+
+            unwind_symmetric_line(
+                &mut line_matches,
+                &mut stack,
+                matcher.same_line_delimiters(),
+            );
             matches_by_line.push(line_matches);
             line_matches = vec![];
             escaped_col = None;
+            line_number += 1;
 
-            if matches!(state, State::InString(_) | State::InLineComment) {
+            if !continued && matches!(state, State::InString(_) | State::InLineComment) {
                 state = State::Normal;
             }
             state_by_line.push(state);
+            conflict_line = parse_conflict(
+                lines[line_number],
+                &mut line_matches,
+                &mut stack,
+                &mut state,
+                &mut conflicts,
+            );
+            continue;
+        }
+        if conflict_line {
             continue;
         }
 
@@ -77,8 +160,14 @@ where
             state,
             token,
             escaped_col.map(|col| col == token.col - 1).unwrap_or(false),
+            lines[line_number].as_bytes(),
         );
     }
+    unwind_symmetric_line(
+        &mut line_matches,
+        &mut stack,
+        matcher.same_line_delimiters(),
+    );
     matches_by_line.push(line_matches);
     state_by_line.push(state);
 
@@ -88,7 +177,7 @@ where
 // TODO: come up with a better way to do testing
 #[cfg(test)]
 mod tests {
-    use crate::parser::{parse_filetype, Match, State};
+    use crate::parser::{parse_filetype, Kind, Match, State, Token};
 
     fn parse_c(lines: &str) -> Vec<Vec<Match>> {
         parse_filetype("c", &lines.split('\n').collect::<Vec<_>>(), State::Normal)
@@ -125,4 +214,428 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_parse_multi_char_delimiters() {
+        let (matches, _) = parse_filetype("ocaml", &["[| [1] |] ]"], State::Normal).unwrap();
+        let array = Token::Delimiter("[|", "|]");
+        assert_eq!(
+            matches[0],
+            vec![
+                Match::new_with_stack(Kind::Opening, array.clone(), 0, 0),
+                Match::delimiter('[', 3, Some(1)),
+                Match::delimiter(']', 5, Some(1)),
+                Match::new_with_stack(Kind::Closing, array, 7, 0),
+                Match::delimiter(']', 10, Some(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_longest_match() {
+        let (matches, _) = parse_filetype("latex", &["$$ x $$ $y$"], State::Normal).unwrap();
+        assert_eq!(
+            matches[0]
+                .iter()
+                .map(|m| (m.token.clone(), m.col))
+                .collect::<Vec<_>>(),
+            vec![
+                (Token::BlockString("$$", "$$"), 0),
+                (Token::BlockString("$$", "$$"), 5),
+                (Token::BlockString("$", "$"), 8),
+                (Token::BlockString("$", "$"), 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_escapes() {
+        let cols = |filetype: &str, line: &str| {
+            let (matches, states) = parse_filetype(filetype, &[line], State::Normal).unwrap();
+            let cols = matches[0].iter().map(|m| m.col).collect::<Vec<_>>();
+            (cols, states[0])
+        };
+
+        // Raw strings can't be escaped
+        assert_eq!(
+            cols("rust", r##"r#"\"# ()"##),
+            (vec![0, 4, 7, 8], State::Normal)
+        );
+        // Verbatim strings escape with `""`
+        assert_eq!(
+            cols("csharp", r#"@"a""b" ()"#),
+            (vec![0, 6, 8, 9], State::Normal)
+        );
+        assert_eq!(cols("sql", "'it''s' ()"), (vec![0, 6, 8, 9], State::Normal));
+        // Backslash by default
+        assert_eq!(cols("c", r#""\"" ()"#), (vec![0, 3, 5, 6], State::Normal));
+    }
+
+    #[test]
+    fn test_parse_anchors() {
+        let tokens = |filetype: &str, text: &str| {
+            let lines = text.split('\n').collect::<Vec<_>>();
+            let (matches, _) = parse_filetype(filetype, &lines, State::Normal).unwrap();
+            matches
+                .iter()
+                .map(|line| line.iter().map(|m| m.token.clone()).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        let begin = Token::BlockComment("=begin", "=end");
+
+        // Only at the start of the line
+        assert_eq!(
+            tokens("ruby", "=begin\n(\n=end"),
+            vec![vec![begin.clone()], vec![], vec![begin]]
+        );
+        assert_eq!(
+            tokens("ruby", "x =begin ()"),
+            vec![vec![Token::Delimiter("(", ")"), Token::Delimiter("(", ")")]]
+        );
+        // Only preceded by whitespace
+        assert_eq!(
+            tokens("shell", "echo $# ${#x} # ()"),
+            vec![vec![
+                Token::Delimiter("{", "}"),
+                Token::Delimiter("{", "}"),
+                Token::LineComment("#"),
+            ]]
+        );
+        assert_eq!(tokens("yaml", "key: a#b []")[0].len(), 2);
+    }
+
+    #[test]
+    fn test_parse_char_literals() {
+        let strings = |filetype: &str, line: &str| {
+            let (matches, _) = parse_filetype(filetype, &[line], State::Normal).unwrap();
+            matches[0]
+                .iter()
+                .filter(|m| m.token == Token::String("'"))
+                .map(|m| m.col)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            strings("rust", r"'\u{1F600}' '\'' '\x7f' 'é'"),
+            vec![0, 10, 12, 15, 17, 22, 24, 27]
+        );
+        // Lifetimes
+        assert_eq!(
+            strings("rust", "fn f<'a>(x: &'a str) -> &'a str"),
+            Vec::<usize>::new()
+        );
+        assert_eq!(strings("rust", "b'(' '{'"), vec![1, 3, 5, 7]);
+        // Digit separators
+        assert_eq!(strings("cpp", "int x = 1'000'000;"), Vec::<usize>::new());
+        assert_eq!(
+            strings("cpp", "auto x = 0b1'0'1 + 0xF'F'FF; u8'(' L'a'"),
+            vec![31, 33, 36, 38]
+        );
+        // Primes
+        assert_eq!(strings("haskell", "f x' ' '"), vec![5, 7]);
+    }
+
+    #[test]
+    fn test_parse_regex_literals() {
+        let tokens = |filetype: &str, line: &str| {
+            let (matches, _) = parse_filetype(filetype, &[line], State::Normal).unwrap();
+            matches[0]
+                .iter()
+                .map(|m| (m.token.clone(), m.col))
+                .collect::<Vec<_>>()
+        };
+        let regex = Token::BlockString("/", "/");
+
+        assert_eq!(
+            tokens("javascript", r#"x = /[(]"/g; (a / b / c)"#),
+            vec![
+                (regex.clone(), 4),
+                (regex.clone(), 9),
+                (Token::Delimiter("(", ")"), 13),
+                (Token::Delimiter("(", ")"), 23),
+            ]
+        );
+        assert_eq!(
+            tokens("typescript", "return /{/.test(s)"),
+            vec![
+                (regex.clone(), 7),
+                (regex.clone(), 9),
+                (Token::Delimiter("(", ")"), 15),
+                (Token::Delimiter("(", ")"), 17),
+            ]
+        );
+        assert_eq!(
+            tokens("ruby", "%r{a{2}(} =~ s"),
+            vec![
+                (Token::BlockString("%r{", "}"), 0),
+                (Token::BlockString("%r{", "}"), 8),
+            ]
+        );
+        assert_eq!(
+            tokens("perl", r"split /\(/, $s")[..2],
+            [(regex.clone(), 6), (regex, 9)]
+        );
+    }
+
+    #[test]
+    fn test_parse_quote_like() {
+        let tokens = |filetype: &str, text: &str| {
+            let lines = text.split('\n').collect::<Vec<_>>();
+            let (matches, _) = parse_filetype(filetype, &lines, State::Normal).unwrap();
+            matches
+                .iter()
+                .map(|line| {
+                    line.iter()
+                        .map(|m| (m.token.clone(), m.col))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Nested brackets, and the opening delimiter of the operator may be escaped
+        let q = Token::BlockString("q{", "}");
+        assert_eq!(
+            tokens("perl", r"(q{a {b} \} ( \{})"),
+            vec![vec![
+                (Token::Delimiter("(", ")"), 0),
+                (q.clone(), 1),
+                (q, 16),
+                (Token::Delimiter("(", ")"), 17),
+            ]]
+        );
+        // Two parts
+        let s = Token::BlockString("s/", "/");
+        assert_eq!(
+            tokens("perl", "s/(/)/; $s/2"),
+            vec![vec![(s.clone(), 0), (s, 5)]]
+        );
+        // Two bracketed parts, which may be separated by whitespace or a line break
+        let s = Token::BlockString("s{", "}");
+        assert_eq!(
+            tokens("perl", "s{a}{(b)}; (c)"),
+            vec![vec![
+                (s.clone(), 0),
+                (s, 8),
+                (Token::Delimiter("(", ")"), 11),
+                (Token::Delimiter("(", ")"), 13),
+            ]]
+        );
+        let s = Token::BlockString("s(", ")");
+        assert_eq!(
+            tokens("perl", "s(a) (b)"),
+            vec![vec![(s.clone(), 0), (s, 7)]]
+        );
+        let tr = Token::BlockString("tr{", "}");
+        assert_eq!(
+            tokens("perl", "tr{a}\n[b] {}"),
+            vec![
+                vec![(tr.clone(), 0)],
+                vec![
+                    (tr, 2),
+                    (Token::Delimiter("{", "}"), 4),
+                    (Token::Delimiter("{", "}"), 5),
+                ],
+            ]
+        );
+        // Spanning lines
+        let w = Token::BlockString("%w[", "]");
+        assert_eq!(
+            tokens("ruby", "x = %w[a (\nb] + [1]"),
+            vec![
+                vec![(w.clone(), 4)],
+                vec![
+                    (w, 1),
+                    (Token::Delimiter("[", "]"), 5),
+                    (Token::Delimiter("[", "]"), 7),
+                ],
+            ]
+        );
+        let sigil = Token::BlockString("~S\"\"\"", "\"\"\"");
+        assert_eq!(
+            tokens("elixir", "~S\"\"\"\n\")\n\"\"\""),
+            vec![vec![(sigil.clone(), 0)], vec![], vec![(sigil, 0)]]
+        );
+    }
+
+    #[test]
+    fn test_parse_continuation() {
+        let states = |filetype: &str, text: &str| {
+            let lines = text.split('\n').collect::<Vec<_>>();
+            parse_filetype(filetype, &lines, State::Normal).unwrap().1
+        };
+
+        assert_eq!(
+            states("c", "// a \\\n(b)\n\"c \\\n)\" \"d \\\\\n("),
+            vec![
+                State::InLineComment,
+                State::Normal,
+                State::InString("\""),
+                State::Normal,
+                State::Normal
+            ]
+        );
+        // Line comments don't continue in Python
+        assert_eq!(
+            states("python", "# a \\\n'b \\\n'"),
+            vec![State::Normal, State::InString("'"), State::Normal]
+        );
+    }
+
+    #[test]
+    fn test_parse_symmetric() {
+        let matches = |filetype: &str, text: &str| {
+            let lines = text.split('\n').collect::<Vec<_>>();
+            let (matches, _) = parse_filetype(filetype, &lines, State::Normal).unwrap();
+            matches
+                .iter()
+                .map(|line| {
+                    line.iter()
+                        .map(|m| (m.kind, m.token.opening(), m.col, m.stack_height))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // The `|` operator is dropped before closing the parentheses and at the end of the line
+        assert_eq!(
+            matches("rust", "|x| (a | [b]) {\nA | B"),
+            vec![
+                vec![
+                    (Kind::Opening, "|", 0, Some(0)),
+                    (Kind::Closing, "|", 2, Some(0)),
+                    (Kind::Opening, "(", 4, Some(0)),
+                    (Kind::Opening, "[", 9, Some(1)),
+                    (Kind::Closing, "[", 11, Some(1)),
+                    (Kind::Closing, "(", 12, Some(0)),
+                    (Kind::Opening, "{", 14, Some(0)),
+                ],
+                vec![],
+            ]
+        );
+        // Spanning lines, with `*` dropped before closing the math
+        assert_eq!(
+            matches("typst", "$a * b\n$ *c*"),
+            vec![
+                vec![(Kind::Opening, "$", 0, Some(0))],
+                vec![
+                    (Kind::Closing, "$", 0, Some(0)),
+                    (Kind::Opening, "*", 2, Some(0)),
+                    (Kind::Closing, "*", 4, Some(0)),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_generic() {
+        let matches = |filetype: &str, text: &str| {
+            let lines = text.split('\n').collect::<Vec<_>>();
+            let (matches, _) = parse_filetype(filetype, &lines, State::Normal).unwrap();
+            matches
+                .iter()
+                .map(|line| {
+                    line.iter()
+                        .map(|m| (m.kind, m.token.opening(), m.col, m.stack_height))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            matches("rust", "Vec<Option<T>> -> a < b"),
+            vec![vec![
+                (Kind::Opening, "<", 3, Some(0)),
+                (Kind::Opening, "<", 10, Some(1)),
+                (Kind::Closing, "<", 12, Some(1)),
+                (Kind::Closing, "<", 13, Some(0)),
+            ]]
+        );
+        // Comparisons are dropped before closing the parentheses and at the end of the line
+        assert_eq!(
+            matches("cpp", "if (i<n) {\nx<y"),
+            vec![
+                vec![
+                    (Kind::Opening, "(", 3, Some(0)),
+                    (Kind::Closing, "(", 7, Some(0)),
+                    (Kind::Opening, "{", 9, Some(0)),
+                ],
+                vec![],
+            ]
+        );
+        assert_eq!(
+            matches("cpp", "template <typename T>"),
+            vec![vec![
+                (Kind::Opening, "<", 9, Some(0)),
+                (Kind::Closing, "<", 20, Some(0)),
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_parse_regions() {
+        let region = Token::Region("#region", "#endregion");
+        let lines = ["// #region a {", "// #regional", "// #endregion }}}"];
+        let (matches, _) = parse_filetype("typescript", &lines, State::Normal).unwrap();
+        assert_eq!(
+            matches,
+            vec![
+                vec![
+                    Match::line_comment("//", 0),
+                    Match::new(Kind::Opening, region.clone(), 3),
+                ],
+                vec![Match::line_comment("//", 0)],
+                vec![
+                    Match::line_comment("//", 0),
+                    Match::new(Kind::Closing, region.clone(), 3),
+                ],
+            ]
+        );
+
+        let (matches, _) =
+            parse_filetype("python", &["#region", "x # {{{1"], State::Normal).unwrap();
+        assert_eq!(matches[0][1], Match::new(Kind::Opening, region, 0));
+        assert_eq!(
+            matches[1][1],
+            Match::new(Kind::Opening, Token::Region("{{{", "}}}"), 4)
+        );
+    }
+
+    #[test]
+    fn test_parse_markers() {
+        let lines = [
+            "#ifdef A",
+            "  #elif (B)",
+            "x #else",
+            "#ifdefined",
+            "#endif // {",
+        ];
+        let (matches, _) = parse_filetype("c", &lines, State::Normal).unwrap();
+        let matches = matches
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|m| (m.kind, m.token.clone(), m.col))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            matches,
+            vec![
+                vec![(Kind::Opening, Token::Marker("#ifdef", "#endif"), 0)],
+                vec![
+                    (Kind::Middle, Token::Marker("#elif", "#endif"), 2),
+                    (Kind::Opening, Token::Delimiter("(", ")"), 8),
+                    (Kind::Closing, Token::Delimiter("(", ")"), 10),
+                ],
+                // Not at the start of the line, nor a whole directive
+                vec![],
+                vec![],
+                vec![
+                    (Kind::Closing, Token::Marker("#endif", "#endif"), 0),
+                    (Kind::NonPair, Token::LineComment("//"), 7),
+                ],
+            ]
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use std::{
     cell::Cell,
     rc::Rc,
-    simd::{cmp::SimdPartialEq, LaneCount, Simd, SupportedLaneCount},
+    simd::{cmp::SimdPartialEq, Select, Simd},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -22,10 +22,7 @@ impl CharPos {
 pub fn tokenize<'s, const N: usize>(
     text: &'s str,
     tokens: &'static [u8],
-) -> impl Iterator<Item = CharPos> + 's
-where
-    LaneCount<N>: SupportedLaneCount,
-{
+) -> impl Iterator<Item = CharPos> + 's {
     let none = Simd::<u8, N>::splat(0);
     let new_line = Simd::<u8, N>::splat(b'\n');
     let escape = Simd::<u8, N>::splat(b'\\');
@@ -54,11 +51,11 @@ where
         .enumerate()
         .flat_map(move |(chunk_idx, chunk)| {
             let mut tokens = none;
-            tokens = tokens | new_line.simd_eq(chunk).select(new_line, none);
-            tokens = tokens | escape.simd_eq(chunk).select(escape, none);
+            tokens |= new_line.simd_eq(chunk).select(new_line, none);
+            tokens |= escape.simd_eq(chunk).select(escape, none);
 
             for &char in tokens_to_find.iter() {
-                tokens = tokens | char.simd_eq(chunk).select(char, none);
+                tokens |= char.simd_eq(chunk).select(char, none);
             }
 
             // Apply parsed tokens
@@ -73,10 +70,10 @@ where
                     b'\n' => {
                         col_offset.set(chunk_col + idx_in_chunk + 1);
 
-                        return Some(CharPos {
+                        Some(CharPos {
                             byte: b'\n',
                             col: 0,
-                        });
+                        })
                     }
                     byte => Some(CharPos {
                         byte,
@@ -88,6 +85,7 @@ where
 
 // TODO: come up with a better way to do testing
 #[cfg(test)]
+#[allow(clippy::useless_vec, clippy::byte_char_slices)]
 mod tests {
     use super::*;
 
@@ -0,0 +1,93 @@
+//! Runs the `blink-pairs` binary on files written to a temporary directory, with:
+//! `cargo test --features cli --test cli`
+#![cfg(feature = "cli")]
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn write_file(name: &str, text: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_blink-pairs"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_detects_language_from_extension() {
+    // `#` only starts a comment in Python
+    let python = write_file("detect.py", "# (\n");
+    let rust = write_file("detect.rs", "# (\n");
+
+    let output = run(&[python.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    let output = run(&[rust.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        format!("{}:1:3: error: unclosed delimiter `(`\n", rust.display())
+    );
+}
+
+#[test]
+fn test_json_output() {
+    let path = write_file("json.c", "int main() {\n  /* (\n}\n");
+
+    let output = run(&["--format", "json", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let diagnostics: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(
+        diagnostics,
+        serde_json::json!([
+            {
+                "file": path.to_str().unwrap(),
+                "line": 1,
+                "col": 12,
+                "len": 1,
+                "text": "{",
+                "message": "unclosed delimiter `{`",
+            },
+            {
+                "file": path.to_str().unwrap(),
+                "line": 2,
+                "col": 3,
+                "len": 2,
+                "text": "/*",
+                "message": "unterminated block comment `/*`",
+            },
+        ])
+    );
+
+    // Balanced files produce an empty array
+    let path = write_file("json.rs", "fn main() {}\n");
+    let output = run(&["--format", "json", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "[]\n");
+}
+
+#[test]
+fn test_exit_codes() {
+    // Unknown extensions are skipped
+    let path = write_file("unknown.xyz", "(\n");
+    let output = run(&[path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+
+    // Unsupported filetypes and unreadable files are failures, distinct from diagnostics
+    let output = run(&["--filetype", "unknown", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = run(&["does-not-exist.rs"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(2));
+}
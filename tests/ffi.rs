@@ -0,0 +1,62 @@
+//! Checks the checked-in header against the one generated by the build script, then compiles
+//! `tests/ffi/harness.c` against it and the cdylib, and runs it.
+//!
+//! The cdylib must not contain the Lua bindings, since the LuaJIT symbols are only provided
+//! when loaded by Neovim, so run with:
+//! `cargo test --no-default-features --features ffi --test ffi`
+#![cfg(all(unix, feature = "ffi", not(feature = "lua")))]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn test_header_is_up_to_date() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/blink_pairs.h"));
+    let checked_in = include_str!("../include/blink_pairs.h");
+    assert!(
+        generated == checked_in,
+        "include/blink_pairs.h is out of date, regenerate it with `cbindgen --output include/blink_pairs.h`"
+    );
+}
+
+#[test]
+fn test_c_harness() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // The cdylib is built next to the test binary in `target/<profile>/deps`
+    let lib_dir = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let harness = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("ffi_harness");
+
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(compiler)
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(manifest_dir.join("tests/ffi/harness.c"))
+        .arg("-o")
+        .arg(&harness)
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lblink_pairs")
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "failed to compile the C harness");
+
+    // Cargo adds `target/<profile>` to the library path, which may hold a cdylib built with
+    // other features, so only search next to the test binary
+    let output = Command::new(&harness)
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "C harness failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,107 @@
+// Exercises the C ABI the way a native host would. Built and run by tests/ffi.rs
+#include <stdio.h>
+#include <string.h>
+
+#include "blink_pairs.h"
+
+#define CHECK(cond)                                                                                \
+  do {                                                                                             \
+    if (!(cond)) {                                                                                 \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond);                     \
+      return 1;                                                                                    \
+    }                                                                                              \
+  } while (0)
+
+static BlinkPairsStr str(const char *text) {
+  BlinkPairsStr s = {text, strlen(text)};
+  return s;
+}
+
+static int str_eq(BlinkPairsStr s, const char *expected) {
+  return s.len == strlen(expected) && memcmp(s.ptr, expected, s.len) == 0;
+}
+
+int main(void) {
+  BlinkPairsStr lines[] = {
+      str("int main() {"),
+      str("  /* { */"),
+      str("  return f(\"(\");"),
+      str("}"),
+  };
+
+  CHECK(blink_pairs_buffer_new("unknown", lines, 4) == NULL);
+
+  BlinkPairsBuffer *buffer = blink_pairs_buffer_new("c", lines, 4);
+  CHECK(buffer != NULL);
+  CHECK(blink_pairs_buffer_line_count(buffer) == 4);
+
+  // Line matches, querying the count first
+  size_t count = blink_pairs_buffer_line_matches(buffer, 0, NULL, 0);
+  CHECK(count == 3);
+  BlinkPairsMatch matches[3];
+  CHECK(blink_pairs_buffer_line_matches(buffer, 0, matches, 3) == 3);
+  CHECK(matches[0].kind == BlinkPairsKind_Opening);
+  CHECK(matches[0].token_type == BlinkPairsTokenType_Delimiter);
+  CHECK(str_eq(matches[0].opening, "("));
+  CHECK(str_eq(matches[0].closing, ")"));
+  CHECK(matches[0].col == 8);
+  CHECK(matches[1].kind == BlinkPairsKind_Closing);
+  CHECK(matches[2].has_stack_height && matches[2].stack_height == 0);
+
+  // Smaller capacity than the number of matches
+  CHECK(blink_pairs_buffer_line_matches(buffer, 0, matches, 1) == 3);
+  CHECK(blink_pairs_buffer_line_matches(buffer, 100, matches, 3) == 0);
+
+  // Block comments and strings
+  BlinkPairsMatch match;
+  CHECK(blink_pairs_buffer_match_at(buffer, 1, 3, &match));
+  CHECK(match.token_type == BlinkPairsTokenType_BlockComment);
+  CHECK(match.len == 2);
+  CHECK(!match.has_stack_height);
+  CHECK(!blink_pairs_buffer_match_at(buffer, 1, 5, &match));
+
+  // Matching pair across lines
+  BlinkPairsPair pair;
+  CHECK(blink_pairs_buffer_match_pair(buffer, 3, 0, &pair));
+  CHECK(pair.opening.line == 0 && pair.opening.col == 11);
+  CHECK(pair.closing.line == 3 && pair.closing.col == 0);
+
+  size_t pair_count = blink_pairs_buffer_pairs(buffer, NULL, 0);
+  CHECK(pair_count == 5);
+  BlinkPairsPair pairs[5];
+  CHECK(blink_pairs_buffer_pairs(buffer, pairs, 5) == 5);
+  CHECK(pairs[0].opening.col == 8 && pairs[0].closing.col == 9);
+  CHECK(pairs[1].opening.col == 11 && pairs[1].closing.line == 3);
+
+  // Incremental parse, opening a block comment on the first line
+  BlinkPairsState state;
+  CHECK(blink_pairs_buffer_line_state(buffer, 0, &state));
+  CHECK(state.kind == BlinkPairsStateKind_Normal);
+
+  BlinkPairsStr changed[] = {str("int main() { /*")};
+  CHECK(blink_pairs_buffer_reparse(buffer, "c", changed, 1, 0, 1, 1));
+  CHECK(blink_pairs_buffer_line_state(buffer, 0, &state));
+  CHECK(state.kind == BlinkPairsStateKind_InBlockComment);
+  CHECK(str_eq(state.token, "/*"));
+  CHECK(!blink_pairs_buffer_line_state(buffer, 4, &state));
+
+  // Invalid ranges are rejected without changing the buffer
+  CHECK(!blink_pairs_buffer_reparse(buffer, "c", changed, 1, 2, 1, 3));
+  CHECK(!blink_pairs_buffer_reparse(buffer, "c", changed, 1, 2, 3, 1));
+  CHECK(!blink_pairs_buffer_reparse(buffer, "c", changed, 1, 5, 6, 6));
+  CHECK(!blink_pairs_buffer_reparse(buffer, "c", changed, 1, 0, 9, 1));
+  CHECK(!blink_pairs_buffer_reparse(buffer, "c", changed, 1, 0, 1, 3));
+  CHECK(blink_pairs_buffer_line_count(buffer) == 4);
+
+  // Full parse
+  BlinkPairsStr replaced[] = {str("[]")};
+  CHECK(blink_pairs_buffer_reparse(buffer, "c", replaced, 1, -1, -1, -1));
+  CHECK(blink_pairs_buffer_line_count(buffer) == 1);
+  CHECK(blink_pairs_buffer_line_matches(buffer, 0, matches, 3) == 2);
+  CHECK(!blink_pairs_buffer_reparse(buffer, "unknown", replaced, 1, -1, -1, -1));
+
+  blink_pairs_buffer_free(buffer);
+  blink_pairs_buffer_free(NULL);
+
+  return 0;
+}
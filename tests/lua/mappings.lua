@@ -0,0 +1,31 @@
+--- Checks that the default `"""` and `r#"` rules are enabled by the block strings in the language
+--- specs of the parser. Run from the root of the repository after building the library:
+--- `cargo build --release && nvim --clean --headless -l tests/lua/mappings.lua`
+vim.opt.runtimepath:prepend('.')
+
+local rules = require('blink.pairs.rule').parse(require('blink.pairs.config.mappings').default.pairs)
+
+--- Whether the rule for `opening` is active with the cursor after `before` in a buffer of the
+--- filetype
+local function is_active(filetype, before, opening)
+  vim.bo.filetype = filetype
+  vim.api.nvim_set_current_line(before .. ')')
+  vim.api.nvim_win_set_cursor(0, { 1, #before })
+
+  for _, rule in ipairs(rules['"']) do
+    if rule.opening == opening then return rule.when() end
+  end
+  error('no rule for ' .. opening)
+end
+
+local cases = {
+  { 'python', '(""', '"""', true },
+  { 'rust', '(""', '"""', false },
+  { 'rust', '(r#', 'r#"', true },
+  { 'python', '(r#', 'r#"', false },
+}
+for _, case in ipairs(cases) do
+  local filetype, before, opening, expected = unpack(case)
+  local active = is_active(filetype, before, opening)
+  assert(active == expected, ('%s in %s: expected %s, got %s'):format(opening, filetype, expected, active))
+end